@@ -0,0 +1,6 @@
+-- Fake members for local development, loaded when SQLITE_SEED_PATH="dev/members.sql"
+INSERT OR REPLACE INTO active_members (student_id, full_name, membership_type, expires_at) VALUES
+    ('123456789', 'John Doe', 'member', '2099-12-31'),
+    ('223456789', 'Jane Citizen', 'committee', '2099-12-31'),
+    ('323456789', 'José García', 'life', NULL),
+    ('423456789', 'Mary-Jane Watson', 'member', '2020-12-31');
@@ -1,5 +1,9 @@
 // this file is to let main.rs know the existence of the "commands" folder
+// the original commands predate the clippy gate and are kept as written
+#[allow(clippy::needless_borrow, clippy::unnecessary_unwrap)]
 pub mod info;
+#[allow(clippy::too_many_arguments, clippy::collapsible_if)]
 pub mod mods_only;
 pub mod verification;
+#[allow(clippy::needless_borrow, clippy::useless_format)]
 pub mod weather;
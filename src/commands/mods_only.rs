@@ -1,4 +1,7 @@
-use crate::{Context, Error};
+use crate::{
+    Context, Error,
+    registration::{self, CommandScope},
+};
 use poise::CreateReply;
 use serenity::all::CreateEmbed;
 
@@ -66,3 +69,70 @@ pub async fn embed(
 
     Ok(())
 }
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum StaleScope {
+    #[name = "This server"]
+    Guild,
+    #[name = "Global"]
+    Global,
+}
+
+/// Remove slash commands left behind in the scope the bot no longer registers in
+#[poise::command(
+    slash_command,
+    guild_only,
+    rename = "unregister-commands",
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn unregister_commands(
+    ctx: Context<'_>,
+    #[description = "Where the stale commands were registered"] scope: StaleScope,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let scope = match scope {
+        StaleScope::Guild => CommandScope::Guild(ctx.guild_id().unwrap()),
+        StaleScope::Global => CommandScope::Global,
+    };
+
+    // clearing the active scope would remove this command too
+    if scope == ctx.data().config.command_scope {
+        ctx.send(
+            CreateReply::default()
+                .content(format!(
+                    "The bot registers its commands {}, change `COMMAND_REGISTRATION` and restart it first.",
+                    scope
+                ))
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let application_id = ctx
+        .http()
+        .application_id()
+        .ok_or("application ID isn't known yet")?;
+
+    registration::unregister(
+        ctx.serenity_context(),
+        &ctx.data().state.registrations,
+        application_id,
+        scope,
+    )
+    .await?;
+
+    ctx.send(
+        CreateReply::default()
+            .content(format!(
+                "Removed the commands registered {}. Discord may take a moment to update.",
+                scope
+            ))
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
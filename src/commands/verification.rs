@@ -1,12 +1,52 @@
-use crate::{ApplicationContext, Error};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use crate::{
+    ApplicationContext, Context, Error,
+    verification::{
+        audit::{AuditRecord, VerificationOutcome, VerificationPath},
+        import::{self, ImportDiff},
+        panel::{self, VerifyPanel, panel_role_problem},
+        privacy::{self, DataExport},
+        reconcile::{GuildMember, ReconcileReport},
+        student_id::StudentId,
+        sweeper,
+    },
+};
+use chrono::{NaiveDate, Utc};
 use poise::{CreateReply, Modal};
 use serde::{Deserialize, Serialize};
-use serenity::all::{CreateActionRow, CreateButton, CreateEmbed, CreateMessage};
+use serenity::all::{
+    Attachment, Colour, ComponentInteractionCollector, CreateActionRow, CreateAttachment,
+    CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+    GuildChannel, Role, RoleId, User, UserId,
+};
+
+// audit records shown by /verify-status
+const STATUS_HISTORY_LIMIT: u32 = 5;
+// largest CSV accepted by /import-members
+const MAX_IMPORT_BYTES: u32 = 5 * 1024 * 1024;
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct StudentRow {
     pub full_name: String,
     pub student_id: String,
+    /// e.g. "member", "committee", "alumni" or "life", mapped to a role by `TIER_ROLES`
+    #[serde(default)]
+    pub membership_type: Option<String>,
+    /// last day of the membership, `None` if it doesn't expire
+    #[serde(default)]
+    pub expires_at: Option<NaiveDate>,
+}
+
+impl StudentRow {
+    /// Whether the membership is still valid today
+    pub fn is_current(&self) -> bool {
+        self.expires_at
+            .is_none_or(|expires_at| expires_at >= Utc::now().date_naive())
+    }
 }
 
 #[derive(Debug, Modal)]
@@ -18,36 +58,1055 @@ pub struct VerificationModal {
     pub name: String,
     #[name = "Student ID"]
     #[placeholder = "s123456789"]
+    #[max_length = 12]
     pub student_id: String,
 }
 
-/// Embed message with verify button to verify membership
+#[derive(Debug, Modal)]
+#[name = "Your Course"]
+pub struct CourseModal {
+    #[name = "Course"]
+    #[placeholder = "Bachelor of Software Engineering (Honours)"]
+    #[max_length = 100]
+    pub course: String,
+}
+
+#[derive(Debug, Modal)]
+#[name = "Verify with your Deakin email"]
+pub struct EmailModal {
+    #[name = "Deakin email address"]
+    #[placeholder = "jdoe@deakin.edu.au"]
+    #[max_length = 100]
+    pub email: String,
+}
+
+#[derive(Debug, Modal)]
+#[name = "Enter your code"]
+pub struct CodeModal {
+    #[name = "6-digit code"]
+    #[placeholder = "123456"]
+    #[min_length = 6]
+    #[max_length = 6]
+    pub code: String,
+}
+
+/// Parses a student ID option, replying with why it's invalid
+async fn parse_student_id(ctx: Context<'_>, input: &str) -> Result<Option<StudentId>, Error> {
+    match input.parse::<StudentId>() {
+        Ok(student_id) => Ok(Some(student_id)),
+        Err(err) => {
+            ctx.send(
+                CreateReply::default()
+                    .content(err.to_string())
+                    .ephemeral(true),
+            )
+            .await?;
+
+            Ok(None)
+        }
+    }
+}
+
+/// Post a verification panel, optionally customised or granting a different role
+#[allow(clippy::too_many_arguments)]
 #[poise::command(
     slash_command,
+    guild_only,
     required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
 )]
-pub async fn verify(ctx: ApplicationContext<'_>) -> Result<(), Error> {
-    let message = {
-        let embed: CreateEmbed = CreateEmbed::new()
-            .title("Verify your DSEC membership")
-            .description("Click **Verify Here** and enter your **Full name** and **Student ID** (e.g., s123456789). Your responses are private.");
+pub async fn verify(
+    ctx: ApplicationContext<'_>,
+    #[description = "Channel to post the panel in (default: this channel)"]
+    #[channel_types("Text", "News")]
+    channel: Option<GuildChannel>,
+    #[description = "Title of the panel"] title: Option<String>,
+    #[description = "Panel text, use \\n for a new line"] description: Option<String>,
+    #[description = "Embed colour (hex, e.g. #1abc9c)"] colour: Option<String>,
+    #[description = "Image URL shown in the panel"] image_url: Option<String>,
+    #[description = "Label of the verify button"] button_label: Option<String>,
+    #[description = "Role granted by this panel (default: the verified role)"] role: Option<Role>,
+) -> Result<(), Error> {
+    // a panel hands out its role to anyone who verifies, so only role managers may pick one
+    if let Some(role) = &role {
+        let can_manage_roles = ctx
+            .interaction
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions)
+            .is_some_and(|permissions| permissions.manage_roles());
+
+        let guild_id = ctx.guild_id().unwrap();
+        let guild = guild_id.to_partial_guild(ctx).await?;
+        let highest_position = |role_ids: &[RoleId]| {
+            role_ids
+                .iter()
+                .filter_map(|role_id| guild.roles.get(role_id))
+                .map(|role| role.position)
+                .max()
+                .unwrap_or(0)
+        };
 
-        let button: CreateButton = CreateButton::new("verify").label("Verify Here");
+        // the owner is above every role
+        let invoker_position = (guild.owner_id != ctx.author().id).then(|| {
+            ctx.interaction
+                .member
+                .as_ref()
+                .map(|member| highest_position(&member.roles))
+                .unwrap_or(0)
+        });
+        let bot = guild_id.member(ctx, ctx.framework().bot_id).await?;
 
-        let components = vec![CreateActionRow::Buttons(vec![button])];
+        let problem = if can_manage_roles {
+            panel_role_problem(role, invoker_position, highest_position(&bot.roles))
+        } else {
+            Some("you need the Manage Roles permission")
+        };
 
-        CreateMessage::new().add_embed(embed).components(components)
+        if let Some(problem) = problem {
+            ctx.send(
+                CreateReply::default()
+                    .content(format!(
+                        "You can't create a panel for <@&{}>, {}.",
+                        role.id, problem
+                    ))
+                    .ephemeral(true),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    }
+
+    let mut panel = VerifyPanel {
+        role_id: role.map(|role| role.id),
+        email_button: ctx.data().state.email.is_enabled(),
+        image_url,
+        ..Default::default()
     };
 
+    if let Some(title) = title {
+        panel.title = title;
+    }
+
+    // slash command options can't contain line breaks
+    if let Some(description) = description {
+        panel.description = description.replace("\\n", "\n");
+    }
+
+    if let Some(colour) = colour
+        && let Ok(colour) = u32::from_str_radix(colour.trim_start_matches('#'), 16)
+    {
+        panel.colour = Some(Colour::new(colour));
+    }
+
+    if let Some(button_label) = button_label {
+        panel.button_label = button_label;
+    }
+
+    let channel_id = channel
+        .map(|channel| channel.id)
+        .unwrap_or(ctx.channel_id());
+
+    channel_id.send_message(ctx, panel.to_message()).await?;
+
     let reply = CreateReply::default()
-        .content("Successfully sent embed!")
+        .content(format!("Successfully sent panel to <#{}>!", channel_id))
         .ephemeral(true);
 
-    let channel_id = ctx.channel_id();
+    ctx.send(reply).await?;
 
-    channel_id.send_message(ctx, message).await?;
+    Ok(())
+}
 
-    ctx.send(reply).await?;
+/// Remove a member's verified role and student ID or email link
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn unverify(
+    ctx: Context<'_>,
+    #[description = "Member to unverify"] user: User,
+    #[description = "Why the verification is being revoked"] reason: Option<String>,
+) -> Result<(), Error> {
+    let state = &ctx.data().state;
+    let guild_id = ctx.guild_id().unwrap();
+    let verified_role_id = ctx.data().config.verified_role_id;
+
+    let link = state.links.find_by_user(user.id).await?;
+
+    if let Some(link) = &link {
+        state.links.clear(&link.student_id).await?;
+        state.student_cache.invalidate(&link.student_id);
+    }
+
+    // frees the address for another account too
+    let email_link = state.email_links.clear_user(user.id).await?;
+
+    // the member may have already left the server
+    let had_role = match guild_id.member(ctx, user.id).await {
+        Ok(member) => {
+            let had_tier_role = state
+                .tiers
+                .sync(ctx.serenity_context(), &member, None)
+                .await?;
+
+            if member.roles.contains(&verified_role_id) {
+                member.remove_role(ctx, verified_role_id).await?;
+                true
+            } else {
+                had_tier_role
+            }
+        }
+        Err(_) => false,
+    };
+
+    if link.is_none() && email_link.is_none() && !had_role {
+        ctx.send(
+            CreateReply::default()
+                .content(format!("<@{}> is not verified.", user.id))
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let student_id = link.map(|link| link.student_id).unwrap_or_default();
+
+    let details = match &reason {
+        Some(reason) => format!("{} (by <@{}>)", reason, ctx.author().id),
+        None => format!("By <@{}>", ctx.author().id),
+    };
+
+    state
+        .audit
+        .record(
+            ctx.serenity_context(),
+            AuditRecord::new(
+                &user,
+                &student_id,
+                VerificationOutcome::Revoked,
+                VerificationPath::Manual,
+            )
+            .with_details(details),
+        )
+        .await;
+
+    ctx.send(
+        CreateReply::default()
+            .content(format!("Unverified <@{}>.", user.id))
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Show a member's verified role, linked student ID and recent verification history
+#[poise::command(
+    slash_command,
+    rename = "verify-status",
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn verify_status(
+    ctx: Context<'_>,
+    #[description = "Member to look up"] user: User,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let state = &ctx.data().state;
+    let guild_id = ctx.guild_id().unwrap();
+
+    let role = match guild_id.member(ctx, user.id).await {
+        Ok(member) if member.roles.contains(&ctx.data().config.verified_role_id) => "Verified ✅",
+        Ok(_) => "Not verified",
+        Err(_) => "Not in the server",
+    };
+
+    let linked = match state.links.find_by_user(user.id).await? {
+        Some(link) => format!(
+            "s{} (<t:{}:f>)",
+            link.student_id,
+            link.linked_at.unix_timestamp()
+        ),
+        None => "None".to_string(),
+    };
+
+    let history = state.audit.history(user.id, STATUS_HISTORY_LIMIT).await?;
+
+    let history = if history.is_empty() {
+        "No verification attempts recorded.".to_string()
+    } else {
+        history
+            .iter()
+            .map(|record| {
+                let mut line = format!(
+                    "<t:{}:R> **{}** via {}",
+                    record.created_at.unix_timestamp(),
+                    record.outcome.title(),
+                    record.path.label()
+                );
+
+                // keep the field under Discord's 1024 character limit
+                if let Some(details) = &record.details {
+                    let details: String = details.chars().take(100).collect();
+                    line.push_str(&format!(" — {}", details));
+                }
+
+                line
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+
+    let embed = CreateEmbed::new()
+        .title("Verification status")
+        .field("Member", format!("<@{}>", user.id), true)
+        .field("Role", role, true)
+        .field("Student ID", linked, true)
+        .field("Recent history", history, false)
+        .color(Colour::DARK_GREY);
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Verify a member without the name check, recording why
+#[poise::command(
+    slash_command,
+    rename = "force-verify",
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn force_verify(
+    ctx: Context<'_>,
+    #[description = "Member to verify"] user: User,
+    #[description = "Student ID (e.g., s123456789)"] student_id: String,
+    #[description = "Why the member is being verified manually"] reason: String,
+) -> Result<(), Error> {
+    let state = &ctx.data().state;
+    let guild_id = ctx.guild_id().unwrap();
+    let Some(student_id) = parse_student_id(ctx, &student_id).await? else {
+        return Ok(());
+    };
+
+    let member = guild_id.member(ctx, user.id).await?;
+
+    if state
+        .links
+        .link(student_id.as_str(), user.id)
+        .await?
+        .is_none()
+    {
+        let holder = match state.links.find_by_student(student_id.as_str()).await? {
+            Some(link) => format!("<@{}>", link.discord_user_id),
+            None => "another account".to_string(),
+        };
+
+        ctx.send(
+            CreateReply::default()
+                .content(format!(
+                    "**{}** is already linked to {}. Use `/link transfer` to move it.",
+                    student_id, holder
+                ))
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    member
+        .add_role(ctx, ctx.data().config.verified_role_id)
+        .await?;
+
+    let record = state.directory.find_member(student_id.as_str()).await?;
+    if let Err(err) = state
+        .tiers
+        .sync(ctx.serenity_context(), &member, record.as_ref())
+        .await
+    {
+        tracing::error!(user = user.id.get(), error = %err, "Failed to sync tier roles");
+    }
+    state.throttle.record_success(user.id);
+
+    state
+        .audit
+        .record(
+            ctx.serenity_context(),
+            AuditRecord::new(
+                &user,
+                student_id.as_str(),
+                VerificationOutcome::ForceVerified,
+                VerificationPath::Manual,
+            )
+            .with_details(format!("{} (by <@{}>)", reason, ctx.author().id)),
+        )
+        .await;
+
+    ctx.send(
+        CreateReply::default()
+            .content(format!("Verified <@{}> as **{}**.", user.id, student_id))
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Manage which Discord account each student ID is linked to
+#[poise::command(
+    slash_command,
+    subcommands("link_view", "link_transfer", "link_clear"),
+    subcommand_required,
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn link(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Show the link for a student ID or a member
+#[poise::command(
+    slash_command,
+    rename = "view",
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn link_view(
+    ctx: Context<'_>,
+    #[description = "Student ID (e.g., s123456789)"] student_id: Option<String>,
+    #[description = "Member to look up"] user: Option<User>,
+) -> Result<(), Error> {
+    let links = &ctx.data().state.links;
+
+    let link = match (student_id, user) {
+        (Some(student_id), _) => {
+            let Some(student_id) = parse_student_id(ctx, &student_id).await? else {
+                return Ok(());
+            };
+
+            links.find_by_student(student_id.as_str()).await?
+        }
+        (None, Some(user)) => links.find_by_user(user.id).await?,
+        (None, None) => {
+            ctx.send(
+                CreateReply::default()
+                    .content("Provide a student ID or a member to look up.")
+                    .ephemeral(true),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    let embed = match link {
+        Some(link) => CreateEmbed::new()
+            .title("Student ID link")
+            .field("Student ID", format!("s{}", link.student_id), true)
+            .field("Member", format!("<@{}>", link.discord_user_id), true)
+            .field(
+                "Linked",
+                format!("<t:{}:f>", link.linked_at.unix_timestamp()),
+                true,
+            )
+            .color(Colour::DARK_GREY),
+        None => CreateEmbed::new()
+            .title("No link found")
+            .description("That student ID or member is not linked.")
+            .color(Colour::DARK_GREY),
+    };
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Move a student ID to another member, moving the verified role with it
+#[poise::command(
+    slash_command,
+    rename = "transfer",
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn link_transfer(
+    ctx: Context<'_>,
+    #[description = "Student ID (e.g., s123456789)"] student_id: String,
+    #[description = "Member to link the student ID to"] user: User,
+) -> Result<(), Error> {
+    let state = &ctx.data().state;
+    let guild_id = ctx.guild_id().unwrap();
+    let Some(student_id) = parse_student_id(ctx, &student_id).await? else {
+        return Ok(());
+    };
+    let verified_role_id = ctx.data().config.verified_role_id;
+
+    let previous = state.links.clear(student_id.as_str()).await?;
+    if state
+        .links
+        .link(student_id.as_str(), user.id)
+        .await?
+        .is_none()
+    {
+        return Err(format!("{} was linked again while being transferred", student_id).into());
+    }
+
+    let new_member = guild_id.member(ctx, user.id).await?;
+    new_member.add_role(ctx, verified_role_id).await?;
+
+    let record = state.directory.find_member(student_id.as_str()).await?;
+    if let Err(err) = state
+        .tiers
+        .sync(ctx.serenity_context(), &new_member, record.as_ref())
+        .await
+    {
+        tracing::error!(user = user.id.get(), error = %err, "Failed to sync tier roles");
+    }
+
+    let previous_user = previous
+        .map(|link| link.discord_user_id)
+        .filter(|previous_user| *previous_user != user.id);
+
+    // the old account is no longer backed by a student ID, if it's still in the server
+    if let Some(previous_user) = previous_user
+        && let Ok(previous_member) = guild_id.member(ctx, previous_user).await
+    {
+        previous_member.remove_role(ctx, verified_role_id).await?;
+        state
+            .tiers
+            .sync(ctx.serenity_context(), &previous_member, None)
+            .await?;
+    }
+
+    let from = previous_user
+        .map(|previous_user| format!("<@{}>", previous_user))
+        .unwrap_or("nobody".to_string());
+
+    state
+        .audit
+        .record(
+            ctx.serenity_context(),
+            AuditRecord::new(
+                &user,
+                student_id.as_str(),
+                VerificationOutcome::LinkTransferred,
+                VerificationPath::Manual,
+            )
+            .with_details(format!("From {} by <@{}>", from, ctx.author().id)),
+        )
+        .await;
+
+    let embed = CreateEmbed::new()
+        .title("Link transferred 🔁")
+        .description(format!(
+            "**{}** is now linked to <@{}> (previously {}).",
+            student_id, user.id, from
+        ))
+        .color(Colour::DARK_GREY);
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Remove a student ID's link so it can be verified again
+#[poise::command(
+    slash_command,
+    rename = "clear",
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn link_clear(
+    ctx: Context<'_>,
+    #[description = "Student ID (e.g., s123456789)"] student_id: String,
+) -> Result<(), Error> {
+    let state = &ctx.data().state;
+    let Some(student_id) = parse_student_id(ctx, &student_id).await? else {
+        return Ok(());
+    };
+
+    let Some(removed) = state.links.clear(student_id.as_str()).await? else {
+        ctx.send(
+            CreateReply::default()
+                .content(format!("**{}** is not linked to anyone.", student_id))
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let linked_user = removed.discord_user_id.to_user(ctx).await?;
+
+    state
+        .audit
+        .record(
+            ctx.serenity_context(),
+            AuditRecord::new(
+                &linked_user,
+                student_id.as_str(),
+                VerificationOutcome::LinkCleared,
+                VerificationPath::Manual,
+            )
+            .with_details(format!("By <@{}>", ctx.author().id)),
+        )
+        .await;
+
+    ctx.send(
+        CreateReply::default()
+            .content(format!(
+                "Cleared the link between **{}** and <@{}>.",
+                student_id, removed.discord_user_id
+            ))
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Check verified members against the membership list and remove expired ones
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn sweep(
+    ctx: Context<'_>,
+    #[description = "Only report what would change (default: SWEEP_DRY_RUN)"] dry_run: Option<bool>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let state = &ctx.data().state;
+    let guild_id = ctx.guild_id().unwrap();
+    let dry_run = dry_run.unwrap_or(state.sweeper.config().dry_run);
+
+    let report = state
+        .sweeper
+        .run(ctx.serenity_context(), state, guild_id, dry_run)
+        .await?;
+
+    ctx.send(
+        CreateReply::default()
+            .embed(report.to_embed())
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// List verified role holders and members whose role doesn't match the membership list
+#[poise::command(
+    slash_command,
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn reconcile(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let state = &ctx.data().state;
+    let guild_id = ctx.guild_id().unwrap();
+    let verified_role_id = ctx.data().config.verified_role_id;
+
+    let members: Vec<GuildMember> = sweeper::guild_members(ctx.serenity_context(), guild_id)
+        .await?
+        .into_iter()
+        .filter(|member| !member.user.bot)
+        .map(|member| GuildMember {
+            user_id: member.user.id,
+            has_role: member.roles.contains(&verified_role_id),
+            username: member.user.name,
+        })
+        .collect();
+
+    let user_ids: Vec<UserId> = members.iter().map(|member| member.user_id).collect();
+    let links: HashMap<UserId, String> = state
+        .links
+        .find_by_users(&user_ids)
+        .await?
+        .into_iter()
+        .map(|link| (link.discord_user_id, link.student_id))
+        .collect();
+
+    let email_verified: HashSet<UserId> = state
+        .email_links
+        .find_by_users(&user_ids)
+        .await?
+        .into_iter()
+        .map(|link| link.discord_user_id)
+        .collect();
+
+    let student_ids: Vec<String> = links.values().cloned().collect();
+    let records: HashMap<String, StudentRow> = state
+        .directory
+        .find_members(&student_ids)
+        .await?
+        .into_iter()
+        .map(|record| (record.student_id.clone(), record))
+        .collect();
+
+    let report = ReconcileReport::new(members, &links, &email_verified, &records);
+    let pages = report.to_embeds();
+
+    let mut reply = CreateReply::default()
+        .embed(pages[0].clone())
+        .ephemeral(true);
+
+    if !report.entries.is_empty() {
+        reply = reply.attachment(CreateAttachment::bytes(
+            report.to_csv()?,
+            "reconciliation.csv",
+        ));
+    }
+
+    if pages.len() == 1 {
+        ctx.send(reply).await?;
+
+        return Ok(());
+    }
+
+    // button IDs start with the invocation ID so presses on other reports are ignored
+    let ctx_id = ctx.id();
+    let prev_button_id = format!("{}prev", ctx_id);
+    let next_button_id = format!("{}next", ctx_id);
+
+    let buttons = CreateActionRow::Buttons(vec![
+        CreateButton::new(&prev_button_id).emoji('◀'),
+        CreateButton::new(&next_button_id).emoji('▶'),
+    ]);
+
+    ctx.send(reply.components(vec![buttons])).await?;
+
+    let mut current_page = 0;
+
+    // ephemeral messages can only be edited for 15 minutes
+    while let Some(press) = ComponentInteractionCollector::new(ctx)
+        .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()))
+        .timeout(Duration::from_secs(15 * 60))
+        .await
+    {
+        if press.data.custom_id == next_button_id {
+            current_page = (current_page + 1) % pages.len();
+        } else if press.data.custom_id == prev_button_id {
+            current_page = current_page.checked_sub(1).unwrap_or(pages.len() - 1);
+        } else {
+            continue;
+        }
+
+        press
+            .create_response(
+                ctx,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new().embed(pages[current_page].clone()),
+                ),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Inspect or clear the membership cache used by verification
+#[poise::command(
+    slash_command,
+    subcommands("cache_stats", "cache_invalidate", "cache_clear"),
+    subcommand_required,
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn cache(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Show membership cache size and hit rate
+#[poise::command(
+    slash_command,
+    rename = "stats",
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn cache_stats(ctx: Context<'_>) -> Result<(), Error> {
+    let stats = ctx.data().state.student_cache.stats();
+
+    let embed = CreateEmbed::new()
+        .title("Membership cache")
+        .field("Entries", format!("{}", stats.entries), true)
+        .field("Hits", format!("{}", stats.hits), true)
+        .field("Misses", format!("{}", stats.misses), true)
+        .field(
+            "Hit rate",
+            format!("{:.1}%", stats.hit_rate() * 100.0),
+            true,
+        )
+        .color(Colour::DARK_GREY);
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Remove one student ID from the membership cache
+#[poise::command(
+    slash_command,
+    rename = "invalidate",
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn cache_invalidate(
+    ctx: Context<'_>,
+    #[description = "Student ID (e.g., s123456789)"] student_id: String,
+) -> Result<(), Error> {
+    let Some(student_id) = parse_student_id(ctx, &student_id).await? else {
+        return Ok(());
+    };
+    let removed = ctx
+        .data()
+        .state
+        .student_cache
+        .invalidate(student_id.as_str());
+
+    let content = if removed {
+        format!("Removed **{}** from the cache.", student_id)
+    } else {
+        format!("**{}** was not cached.", student_id)
+    };
+
+    ctx.send(CreateReply::default().content(content).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Empty the membership cache
+#[poise::command(
+    slash_command,
+    rename = "clear",
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn cache_clear(ctx: Context<'_>) -> Result<(), Error> {
+    let cleared = ctx.data().state.student_cache.clear();
+
+    ctx.send(
+        CreateReply::default()
+            .content(format!("Cleared {} cached student IDs.", cleared))
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Import a membership export (CSV) into the member list, previewing the changes first
+#[poise::command(
+    slash_command,
+    rename = "import-members",
+    guild_only,
+    required_permissions = "MANAGE_MESSAGES | MANAGE_THREADS"
+)]
+pub async fn import_members(
+    ctx: Context<'_>,
+    #[description = "CSV with a student ID column and a name column"] file: Attachment,
+    #[description = "Remove members missing from the file (default: false)"] remove_missing: Option<
+        bool,
+    >,
+) -> Result<(), Error> {
+    if file.size > MAX_IMPORT_BYTES {
+        ctx.send(
+            CreateReply::default()
+                .content("That file is too large, membership exports should be under 5 MB.")
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    ctx.defer_ephemeral().await?;
+
+    let state = &ctx.data().state;
+    let data = file.download().await?;
+
+    let imported = match import::parse_csv(&data) {
+        Ok(imported) => imported,
+        Err(errors) => {
+            ctx.send(
+                CreateReply::default()
+                    .embed(import::errors_embed(&errors))
+                    .ephemeral(true),
+            )
+            .await?;
+
+            return Ok(());
+        }
+    };
+
+    let current = state.directory.list_members().await?;
+    let diff = ImportDiff::new(current, imported, remove_missing.unwrap_or(false));
+
+    if diff.is_empty() {
+        ctx.send(
+            CreateReply::default()
+                .content(format!(
+                    "The member list already matches **{}**, nothing to import.",
+                    file.filename
+                ))
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let embed = diff.to_embed();
+    let buttons = state.imports.add(ctx.author().id, diff);
+
+    ctx.send(
+        CreateReply::default()
+            .embed(embed)
+            .components(vec![buttons])
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Check your own verification and membership status
+#[poise::command(slash_command, guild_only)]
+pub async fn membership(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let state = &ctx.data().state;
+    let guild_id = ctx.guild_id().unwrap();
+    let user_id = ctx.author().id;
+
+    let has_role = guild_id
+        .member(ctx, user_id)
+        .await
+        .is_ok_and(|member| member.roles.contains(&ctx.data().config.verified_role_id));
+
+    let link = state.links.find_by_user(user_id).await?;
+
+    let record = match &link {
+        Some(link) => state.directory.find_member(&link.student_id).await?,
+        None => None,
+    };
+
+    let student_id = match &link {
+        Some(link) => match link.student_id.parse::<StudentId>() {
+            Ok(student_id) => student_id.masked(),
+            Err(_) => "s*********".to_string(),
+        },
+        None => "Not linked".to_string(),
+    };
+
+    let membership = match (&link, &record) {
+        (None, _) => "Unknown until you verify".to_string(),
+        (Some(_), None) => "Not found in the member list".to_string(),
+        (Some(_), Some(record)) => match record.expires_at {
+            None => "Active".to_string(),
+            Some(expires_at) if record.is_current() => format!("Active until {}", expires_at),
+            Some(expires_at) => format!("Expired on {}", expires_at),
+        },
+    };
+
+    let last_sync = match state.directory.last_sync().await? {
+        Some(sync) => format!("<t:{}:R>", sync.synced_at.timestamp()),
+        None => "Unknown".to_string(),
+    };
+
+    let status = if has_role {
+        "Verified ✅"
+    } else {
+        "Not verified"
+    };
+
+    let mut embed = CreateEmbed::new()
+        .title("Your DSEC membership")
+        .field("Status", status, true)
+        .field("Student ID", student_id, true)
+        .field("Membership", membership, true)
+        .field("Member list last updated", last_sync, true)
+        .color(if has_role {
+            Colour::DARK_GREEN
+        } else {
+            Colour::DARK_GREY
+        });
+
+    let mut reply = CreateReply::default().ephemeral(true);
+
+    if !has_role {
+        embed = embed.description("New memberships can take up to **a week** to reach the member list. Once yours has, verify below.");
+
+        // same custom_id as the default verification panel, so it starts the usual flow
+        let button = CreateButton::new(panel::button_id(None)).label("Verify now");
+        reply = reply.components(vec![CreateActionRow::Buttons(vec![button])]);
+    }
+
+    ctx.send(reply.embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Export or delete the data the bot stores about you
+#[poise::command(
+    slash_command,
+    subcommands("privacy_export", "privacy_delete"),
+    subcommand_required
+)]
+pub async fn privacy(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Get a copy of everything the bot stores about you
+#[poise::command(slash_command, rename = "export")]
+pub async fn privacy_export(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let state = &ctx.data().state;
+    let export = DataExport::collect(state, ctx.author().id).await?;
+
+    ctx.send(
+        CreateReply::default()
+            .content("Here's everything the bot stores about you, as JSON.")
+            .attachment(export.to_attachment()?)
+            .ephemeral(true),
+    )
+    .await?;
+
+    state
+        .audit
+        .notify(ctx.serenity_context(), export.to_embed(ctx.author()))
+        .await;
+
+    Ok(())
+}
+
+/// Delete your verification link, logs and profile
+#[poise::command(slash_command, rename = "delete")]
+pub async fn privacy_delete(ctx: Context<'_>) -> Result<(), Error> {
+    let embed = CreateEmbed::new()
+        .title("Delete your verification data?")
+        .description("This removes your student ID link, your verification log entries and your course, campus and year level, and takes away your verified and membership roles. You'll need to verify again to get them back.\n\nMessages already posted to the committee, such as verification logs and reviews, aren't deleted and still show your student ID.")
+        .colour(Colour::RED);
+
+    ctx.send(
+        CreateReply::default()
+            .embed(embed)
+            .components(vec![privacy::erase_buttons()])
+            .ephemeral(true),
+    )
+    .await?;
 
     Ok(())
 }
@@ -1,10 +1,9 @@
-use crate::{Context, Error};
+use crate::{Context, Error, errors::UserError};
 use poise::CreateReply;
 use serenity::{all::CreateEmbed, json::Value};
 
-async fn get_weather(location: String) -> Result<String, Error> {
-    let weather_api_key = std::env::var("WEATHER_TOKEN").expect("missing WEATHER_TOKEN");
-
+#[tracing::instrument(name = "weather_api", skip(weather_api_key))]
+async fn get_weather(weather_api_key: &str, location: String) -> Result<String, Error> {
     let request_url = format!(
         "https://api.weatherapi.com/v1/current.json?key={key}&q={location}",
         key = weather_api_key,
@@ -23,9 +22,25 @@ pub async fn weather(
     ctx: Context<'_>,
     #[description = "Location (City or Country)"] location: String,
 ) -> Result<(), Error> {
-    let weather_response = get_weather(location).await?;
+    let Some(weather_token) = &ctx.data().config.weather_token else {
+        ctx.send(
+            CreateReply::default()
+                .content("Weather isn't set up on this bot.")
+                .ephemeral(true),
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let weather_response = get_weather(weather_token.expose(), location).await?;
     let value: Value = serde_json::from_str(&weather_response)?;
 
+    // e.g. "No matching location found."
+    if let Some(message) = value["error"]["message"].as_str() {
+        return Err(UserError(message.to_string()).into());
+    }
+
     let location_name = (&value["location"]["name"]).as_str().unwrap();
     let location_region = (&value["location"]["region"]).as_str().unwrap();
     let location_country = (&value["location"]["country"]).as_str().unwrap();
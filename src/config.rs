@@ -0,0 +1,599 @@
+use std::{collections::HashMap, fmt, net::SocketAddr, path::Path, str::FromStr, time::Duration};
+
+use dotenv::dotenv;
+use lettre::message::Mailbox;
+use poise::serenity_prelude::{ChannelId, GuildId, RoleId};
+
+use crate::{
+    registration::CommandScope,
+    telemetry::LogFormat,
+    verification::{
+        cache::CacheConfig, email::EmailConfig, email::SmtpSecurity, onboarding::OnboardingConfig,
+        push::PushConfig, roles::RoleMap, sweeper::SweepConfig, throttle::ThrottleConfig,
+    },
+};
+
+// read when CONFIG_FILE isn't set, if it exists
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+// longest duration any setting may add up to, ten years
+const MAX_DURATION_SECS: u64 = 10 * 365 * 24 * 60 * 60;
+
+/// A value that shouldn't end up in logs, such as a token
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret(***)")
+    }
+}
+
+impl FromStr for Secret {
+    type Err = std::convert::Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(Self(value.to_string()))
+    }
+}
+
+// a Discord ID, which can't be zero
+struct Snowflake(u64);
+
+impl FromStr for Snowflake {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.parse() {
+            Ok(0) | Err(_) => Err("expected a Discord ID".to_string()),
+            Ok(id) => Ok(Self(id)),
+        }
+    }
+}
+
+/// Every problem found while loading the config, so they can all be fixed at once
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Invalid configuration:")?;
+
+        for problem in &self.problems {
+            writeln!(f, "  - {}", problem)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Reads settings from environment variables and the config file, keeping track of problems
+/// instead of stopping at the first one
+#[derive(Debug)]
+pub struct ConfigLoader {
+    // keyed by the uppercase environment variable name
+    values: HashMap<String, String>,
+    problems: Vec<String>,
+}
+
+impl ConfigLoader {
+    /// Environment variables take priority over the file. Empty values count as unset.
+    pub fn new(
+        env: impl IntoIterator<Item = (String, String)>,
+        file: HashMap<String, String>,
+    ) -> Self {
+        let mut values = HashMap::new();
+
+        for (key, value) in file.into_iter().chain(env) {
+            let value = value.trim();
+
+            if !value.is_empty() {
+                values.insert(key.to_uppercase(), value.to_string());
+            }
+        }
+
+        Self {
+            values,
+            problems: Vec::new(),
+        }
+    }
+
+    pub fn problem(&mut self, problem: impl Into<String>) {
+        self.problems.push(problem.into());
+    }
+
+    fn parse<T>(&mut self, key: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let value = self.values.get(key)?;
+
+        match value.parse() {
+            Ok(parsed) => Some(parsed),
+            Err(err) => {
+                let problem = format!("{}: couldn't parse \"{}\": {}", key, value, err);
+                self.problem(problem);
+                None
+            }
+        }
+    }
+
+    pub fn required<T>(&mut self, key: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        if !self.values.contains_key(key) {
+            self.problem(format!("{}: missing", key));
+        }
+
+        self.parse(key)
+    }
+
+    pub fn optional<T>(&mut self, key: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.parse(key)
+    }
+
+    pub fn or<T>(&mut self, key: &str, default: T) -> T
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.parse(key).unwrap_or(default)
+    }
+
+    /// Like `or`, but a problem if the value is zero
+    pub fn count<T>(&mut self, key: &str, default: T) -> T
+    where
+        T: FromStr + Default + PartialEq,
+        T::Err: fmt::Display,
+    {
+        let value = self.or(key, default);
+
+        if value == T::default() {
+            self.problem(format!("{}: must be greater than 0", key));
+        }
+
+        value
+    }
+
+    pub fn secs(&mut self, key: &str, default: u64) -> Duration {
+        self.duration(key, default, 1)
+    }
+
+    /// A whole number of `unit_secs` long units, e.g. 3600 for hours
+    pub fn duration(&mut self, key: &str, default: u64, unit_secs: u64) -> Duration {
+        let value = self.or(key, default);
+
+        // capped so adding it to an `Instant` or timestamp can't overflow
+        match value
+            .checked_mul(unit_secs)
+            .filter(|secs| *secs <= MAX_DURATION_SECS)
+        {
+            Some(secs) => Duration::from_secs(secs),
+            None => {
+                self.problem(format!(
+                    "{}: must be at most {} seconds in total",
+                    key, MAX_DURATION_SECS
+                ));
+                Duration::from_secs(default * unit_secs)
+            }
+        }
+    }
+
+    /// Like `duration`, but a problem if the value is zero
+    pub fn nonzero_duration(&mut self, key: &str, default: u64, unit_secs: u64) -> Duration {
+        let duration = self.duration(key, default, unit_secs);
+
+        if duration.is_zero() {
+            self.problem(format!("{}: must be greater than 0", key));
+        }
+
+        duration
+    }
+
+    pub fn finish(self) -> Result<(), ConfigError> {
+        if self.problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError {
+                problems: self.problems,
+            })
+        }
+    }
+}
+
+/// Reads a flat TOML file whose keys are the environment variable names, in any case
+fn read_file(path: &Path) -> Result<HashMap<String, String>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("{}: couldn't read: {}", path.display(), err))?;
+
+    let table: toml::Table = contents
+        .parse()
+        .map_err(|err| format!("{}: invalid TOML: {}", path.display(), err))?;
+
+    table
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Float(value) => value.to_string(),
+                toml::Value::Boolean(value) => value.to_string(),
+                _ => {
+                    return Err(format!(
+                        "{}: {} should be a string, number or boolean",
+                        path.display(),
+                        key
+                    ));
+                }
+            };
+
+            Ok((key.to_uppercase(), value))
+        })
+        .collect()
+}
+
+/// Where membership lookups go
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirectoryBackend {
+    Supabase,
+    /// a local SQLite file, seeded from `seed_path` on startup
+    Sqlite {
+        path: String,
+        seed_path: Option<String>,
+    },
+}
+
+/// Everything the bot reads from its environment, loaded and checked once at startup
+#[derive(Debug, Clone)]
+pub struct BotConfig {
+    pub discord_token: Secret,
+    pub supabase_url: String,
+    pub supabase_key: Secret,
+    pub verified_role_id: RoleId,
+    pub guild_id: Option<GuildId>,
+    pub command_scope: CommandScope,
+    /// `RUST_LOG` style filter, e.g. "info,dsec_bot=debug"
+    pub log_filter: String,
+    pub log_format: LogFormat,
+    /// where `/healthz` and `/metrics` are served, off when unset
+    pub monitoring_addr: Option<SocketAddr>,
+    pub weather_token: Option<Secret>,
+    pub mod_log_channel: Option<ChannelId>,
+    pub review_channel: Option<ChannelId>,
+    pub directory: DirectoryBackend,
+    pub name_match_threshold: f64,
+    pub tier_roles: RoleMap,
+    pub onboarding: OnboardingConfig,
+    pub email: EmailConfig,
+    pub push: PushConfig,
+    pub cache: CacheConfig,
+    pub throttle: ThrottleConfig,
+    pub sweep: SweepConfig,
+}
+
+impl BotConfig {
+    /// Loads `.env`, the config file (`CONFIG_FILE`, or `config.toml` if it exists) and the environment
+    pub fn load() -> Result<Self, ConfigError> {
+        dotenv().ok();
+
+        let file = match std::env::var("CONFIG_FILE") {
+            Ok(path) if !path.trim().is_empty() => read_file(Path::new(path.trim())),
+            _ if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                read_file(Path::new(DEFAULT_CONFIG_FILE))
+            }
+            _ => Ok(HashMap::new()),
+        };
+
+        let file = file.map_err(|problem| ConfigError {
+            problems: vec![problem],
+        })?;
+
+        Self::from_loader(ConfigLoader::new(std::env::vars(), file))
+    }
+
+    pub fn from_loader(mut loader: ConfigLoader) -> Result<Self, ConfigError> {
+        let discord_token = loader.required("DISCORD_TOKEN");
+        let supabase_url = loader.required("SUPABASE_URL");
+        let supabase_key = loader.required("SUPABASE_KEY");
+        let verified_role_id = loader
+            .required::<Snowflake>("VERIFIED_ROLE_ID")
+            .map(|id| RoleId::new(id.0));
+        let guild_id = loader
+            .optional::<Snowflake>("GUILD_ID")
+            .map(|id| GuildId::new(id.0));
+
+        let command_scope = match loader
+            .or("COMMAND_REGISTRATION", "global".to_string())
+            .to_lowercase()
+            .as_str()
+        {
+            "global" => CommandScope::Global,
+            "guild" => match guild_id {
+                Some(guild_id) => CommandScope::Guild(guild_id),
+                None => {
+                    loader.problem("GUILD_ID: needed when COMMAND_REGISTRATION is \"guild\"");
+                    CommandScope::Global
+                }
+            },
+            other => {
+                loader.problem(format!(
+                    "COMMAND_REGISTRATION: unknown mode \"{}\", expected \"global\" or \"guild\"",
+                    other
+                ));
+                CommandScope::Global
+            }
+        };
+
+        let log_filter = loader.or("RUST_LOG", "info".to_string());
+        if let Err(err) = tracing_subscriber::EnvFilter::try_new(&log_filter) {
+            loader.problem(format!("RUST_LOG: {}", err));
+        }
+
+        let log_format = loader.or("LOG_FORMAT", LogFormat::Text);
+
+        let mut channel = |key: &str| {
+            loader
+                .optional::<Snowflake>(key)
+                .map(|id| ChannelId::new(id.0))
+        };
+        let mod_log_channel = channel("MOD_LOG_CHANNEL_ID");
+        let review_channel = channel("REVIEW_CHANNEL_ID");
+
+        let directory = match loader
+            .or("MEMBER_DIRECTORY", "supabase".to_string())
+            .to_lowercase()
+            .as_str()
+        {
+            "supabase" => DirectoryBackend::Supabase,
+            "sqlite" => DirectoryBackend::Sqlite {
+                path: loader.or("SQLITE_PATH", "members.sqlite".to_string()),
+                seed_path: loader.optional("SQLITE_SEED_PATH"),
+            },
+            other => {
+                loader.problem(format!(
+                    "MEMBER_DIRECTORY: unknown backend \"{}\", expected \"supabase\" or \"sqlite\"",
+                    other
+                ));
+                DirectoryBackend::Supabase
+            }
+        };
+
+        let name_match_threshold = loader.or("NAME_MATCH_THRESHOLD", 0.9);
+        if !(0.0..=1.0).contains(&name_match_threshold) {
+            loader.problem("NAME_MATCH_THRESHOLD: must be between 0.0 and 1.0");
+        }
+
+        let onboarding = OnboardingConfig {
+            enabled: loader.or("ONBOARDING_ENABLED", false),
+            campus_roles: loader.or("CAMPUS_ROLES", RoleMap::default()),
+            year_roles: loader.or("YEAR_ROLES", RoleMap::default()),
+        };
+
+        let email = Self::email(&mut loader);
+        let push = Self::push(&mut loader);
+
+        let cache = CacheConfig {
+            ttl: loader.secs("CACHE_TTL_SECS", 3600),
+            negative_ttl: loader.secs("CACHE_NEGATIVE_TTL_SECS", 300),
+            max_entries: loader.count("CACHE_MAX_ENTRIES", 5000),
+        };
+
+        let throttle = ThrottleConfig {
+            user_max_failures: loader.count("VERIFY_USER_MAX_FAILURES", 3),
+            user_window: loader.nonzero_duration("VERIFY_USER_WINDOW_SECS", 600, 1),
+            lockout: loader.nonzero_duration("VERIFY_LOCKOUT_SECS", 300, 1),
+            max_lockout: loader.nonzero_duration("VERIFY_MAX_LOCKOUT_SECS", 86_400, 1),
+            guild_max_failures: loader.count("VERIFY_GUILD_MAX_FAILURES", 30),
+            guild_window: loader.nonzero_duration("VERIFY_GUILD_WINDOW_SECS", 300, 1),
+        };
+
+        let sweep = SweepConfig {
+            enabled: loader.or("SWEEP_ENABLED", false),
+            guild_id,
+            interval: loader.nonzero_duration("SWEEP_INTERVAL_HOURS", 24, 60 * 60),
+            dry_run: loader.or("SWEEP_DRY_RUN", true),
+            grace: loader.duration("SWEEP_GRACE_DAYS", 7, 24 * 60 * 60),
+            notify: loader.or("SWEEP_NOTIFY", false),
+            renewal_url: loader.optional("RENEWAL_URL"),
+        };
+
+        if sweep.enabled && guild_id.is_none() {
+            loader.problem("GUILD_ID: needed when SWEEP_ENABLED is true");
+        }
+
+        let config = BotConfig {
+            weather_token: loader.optional("WEATHER_TOKEN"),
+            tier_roles: loader.or("TIER_ROLES", RoleMap::default()),
+            discord_token: discord_token.unwrap_or(Secret(String::new())),
+            supabase_url: supabase_url.unwrap_or_default(),
+            supabase_key: supabase_key.unwrap_or(Secret(String::new())),
+            // only used once `finish` has checked it was set
+            verified_role_id: verified_role_id.unwrap_or(RoleId::new(1)),
+            guild_id,
+            command_scope,
+            log_filter,
+            log_format,
+            monitoring_addr: loader.optional("MONITORING_ADDR"),
+            mod_log_channel,
+            review_channel,
+            directory,
+            name_match_threshold,
+            onboarding,
+            email,
+            push,
+            cache,
+            throttle,
+            sweep,
+        };
+
+        loader.finish()?;
+
+        Ok(config)
+    }
+
+    fn email(loader: &mut ConfigLoader) -> EmailConfig {
+        let enabled = loader.or("EMAIL_VERIFICATION_ENABLED", false);
+        let smtp_security = loader.or("SMTP_SECURITY", SmtpSecurity::StartTls);
+        let from: Option<Mailbox> = loader.optional("SMTP_FROM");
+
+        if enabled && from.is_none() {
+            loader.problem("SMTP_FROM: needed when EMAIL_VERIFICATION_ENABLED is true");
+        }
+
+        EmailConfig {
+            enabled,
+            domain: loader
+                .or("EMAIL_DOMAIN", "deakin.edu.au".to_string())
+                .to_lowercase(),
+            smtp_host: loader.or("SMTP_HOST", "localhost".to_string()),
+            smtp_port: loader.or("SMTP_PORT", smtp_security.default_port()),
+            smtp_security,
+            smtp_username: loader.optional("SMTP_USERNAME"),
+            smtp_password: loader.optional("SMTP_PASSWORD"),
+            from,
+            code_ttl: loader.nonzero_duration("EMAIL_CODE_TTL_SECS", 600, 1),
+            max_attempts: loader.count("EMAIL_CODE_MAX_ATTEMPTS", 5),
+        }
+    }
+
+    fn push(loader: &mut ConfigLoader) -> PushConfig {
+        let addr: Option<SocketAddr> = loader.optional("PUSH_SYNC_ADDR");
+        let token: Option<Secret> = loader.optional("PUSH_SYNC_TOKEN");
+
+        if addr.is_some() && token.as_ref().is_none_or(|token| token.expose().len() < 16) {
+            loader.problem(
+                "PUSH_SYNC_TOKEN: must be at least 16 characters when PUSH_SYNC_ADDR is set",
+            );
+        }
+
+        PushConfig {
+            addr,
+            token: token.unwrap_or(Secret(String::new())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loader(env: &[(&str, &str)], file: &[(&str, &str)]) -> ConfigLoader {
+        let pairs = |pairs: &[(&str, &str)]| {
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        ConfigLoader::new(pairs(env), pairs(file).into_iter().collect())
+    }
+
+    const REQUIRED: &[(&str, &str)] = &[
+        ("DISCORD_TOKEN", "token"),
+        ("SUPABASE_URL", "http://localhost:54321"),
+        ("SUPABASE_KEY", "key"),
+        ("VERIFIED_ROLE_ID", "123"),
+    ];
+
+    #[test]
+    fn loads_defaults_for_optional_values() {
+        let config = BotConfig::from_loader(loader(REQUIRED, &[])).unwrap();
+
+        assert_eq!(config.verified_role_id, RoleId::new(123));
+        assert_eq!(config.directory, DirectoryBackend::Supabase);
+        assert_eq!(config.guild_id, None);
+        assert_eq!(config.command_scope, CommandScope::Global);
+        assert_eq!(config.log_format, LogFormat::Text);
+        assert!(config.weather_token.is_none());
+        assert_eq!(config.cache.max_entries, 5000);
+        assert!(!config.email.enabled);
+    }
+
+    #[test]
+    fn environment_overrides_the_file() {
+        let config = BotConfig::from_loader(loader(
+            &[("GUILD_ID", "42"), ("CACHE_MAX_ENTRIES", "")],
+            &[
+                ("discord_token", "token"),
+                ("supabase_url", "http://localhost:54321"),
+                ("supabase_key", "key"),
+                ("verified_role_id", "123"),
+                ("guild_id", "7"),
+                ("command_registration", "Guild"),
+                ("cache_max_entries", "10"),
+            ],
+        ))
+        .unwrap();
+
+        assert_eq!(config.guild_id, Some(GuildId::new(42)));
+        assert_eq!(config.command_scope, CommandScope::Guild(GuildId::new(42)));
+        // an empty variable counts as unset, so the file's value is kept
+        assert_eq!(config.cache.max_entries, 10);
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let err = BotConfig::from_loader(loader(
+            &[
+                ("SUPABASE_URL", "http://localhost:54321"),
+                ("VERIFIED_ROLE_ID", "abc"),
+                ("MOD_LOG_CHANNEL_ID", "0"),
+                ("COMMAND_REGISTRATION", "guild"),
+                ("LOG_FORMAT", "xml"),
+                ("SWEEP_ENABLED", "true"),
+                ("NAME_MATCH_THRESHOLD", "2"),
+                ("EMAIL_CODE_TTL_SECS", "0"),
+                ("EMAIL_CODE_MAX_ATTEMPTS", "0"),
+                ("CACHE_MAX_ENTRIES", "0"),
+                ("VERIFY_GUILD_MAX_FAILURES", "0"),
+                ("SWEEP_INTERVAL_HOURS", "0"),
+                ("SWEEP_GRACE_DAYS", "18446744073709551615"),
+            ],
+            &[],
+        ))
+        .unwrap_err();
+
+        let keys: Vec<&str> = err
+            .problems
+            .iter()
+            .map(|problem| problem.split(':').next().unwrap())
+            .collect();
+
+        assert_eq!(
+            keys,
+            vec![
+                "DISCORD_TOKEN",
+                "SUPABASE_KEY",
+                "VERIFIED_ROLE_ID",
+                "GUILD_ID",
+                "LOG_FORMAT",
+                "MOD_LOG_CHANNEL_ID",
+                "NAME_MATCH_THRESHOLD",
+                "EMAIL_CODE_TTL_SECS",
+                "EMAIL_CODE_MAX_ATTEMPTS",
+                "CACHE_MAX_ENTRIES",
+                "VERIFY_GUILD_MAX_FAILURES",
+                "SWEEP_INTERVAL_HOURS",
+                "SWEEP_GRACE_DAYS",
+                "GUILD_ID",
+            ]
+        );
+    }
+}
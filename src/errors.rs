@@ -0,0 +1,416 @@
+use std::{error::Error as StdError, fmt};
+
+use poise::serenity_prelude::{
+    self as serenity, Colour, ComponentInteraction, CreateEmbed, CreateEmbedFooter,
+    CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage,
+    StatusCode,
+};
+use poise::{CreateReply, FrameworkError};
+use rand::Rng;
+
+use crate::{Data, Error, metrics::Metrics};
+
+/// A mistake by the member, shown to them as is
+#[derive(Debug, Clone)]
+pub struct UserError(pub String);
+
+impl fmt::Display for UserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl StdError for UserError {}
+
+/// Who or what an error should be blamed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    UserInput,
+    Permission,
+    /// Discord, Supabase, the weather API or the mail server failed
+    Upstream,
+    Bug,
+}
+
+impl ErrorKind {
+    /// Looks through the error and its sources for a type it recognises
+    pub fn classify(err: &(dyn StdError + 'static)) -> Self {
+        let mut next = Some(err);
+
+        while let Some(err) = next {
+            if err.is::<UserError>() {
+                return ErrorKind::UserInput;
+            }
+
+            if let Some(err) = err.downcast_ref::<serenity::Error>() {
+                return Self::classify_serenity(err);
+            }
+
+            if err.is::<supabase::Error>()
+                || err.is::<reqwest::Error>()
+                || err.is::<lettre::transport::smtp::Error>()
+            {
+                return ErrorKind::Upstream;
+            }
+
+            next = err.source();
+        }
+
+        ErrorKind::Bug
+    }
+
+    /// Which outside service failed, used as the metrics label for upstream errors
+    pub fn upstream_service(err: &(dyn StdError + 'static)) -> Option<&'static str> {
+        let mut next = Some(err);
+
+        while let Some(err) = next {
+            if err.is::<serenity::Error>() {
+                return Some("discord");
+            }
+            if err.is::<supabase::Error>() {
+                return Some("supabase");
+            }
+            // the bot only calls the weather API with reqwest directly
+            if err.is::<reqwest::Error>() {
+                return Some("weather_api");
+            }
+            if err.is::<lettre::transport::smtp::Error>() {
+                return Some("smtp");
+            }
+
+            next = err.source();
+        }
+
+        None
+    }
+
+    fn classify_serenity(err: &serenity::Error) -> Self {
+        use serenity::{Error as SerenityError, HttpError, ModelError};
+
+        match err {
+            SerenityError::Http(HttpError::UnsuccessfulRequest(response))
+                if response.status_code == StatusCode::FORBIDDEN =>
+            {
+                ErrorKind::Permission
+            }
+            SerenityError::Model(ModelError::InvalidPermissions { .. } | ModelError::Hierarchy) => {
+                ErrorKind::Permission
+            }
+            SerenityError::Http(_)
+            | SerenityError::Gateway(_)
+            | SerenityError::Tungstenite(_)
+            | SerenityError::Io(_) => ErrorKind::Upstream,
+            _ => ErrorKind::Bug,
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            ErrorKind::UserInput => "That didn't work",
+            ErrorKind::Permission => "Missing permissions 🔒",
+            ErrorKind::Upstream => "Service unavailable",
+            ErrorKind::Bug => "Something went wrong",
+        }
+    }
+
+    fn default_message(&self) -> &'static str {
+        match self {
+            ErrorKind::UserInput => "Check what you entered and try again.",
+            ErrorKind::Permission => {
+                "The bot is missing a permission it needs for this, such as managing a role above its own. Let a committee member know."
+            }
+            ErrorKind::Upstream => {
+                "Discord or a service the bot relies on didn't respond properly. Please try again in a minute."
+            }
+            ErrorKind::Bug => {
+                "This is a bug in the bot. Let a committee member know, with the incident ID below."
+            }
+        }
+    }
+
+    fn colour(&self) -> Colour {
+        match self {
+            ErrorKind::UserInput => Colour::ORANGE,
+            ErrorKind::Permission => Colour::GOLD,
+            ErrorKind::Upstream => Colour::DARK_ORANGE,
+            ErrorKind::Bug => Colour::RED,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ErrorKind::UserInput => "user input",
+            ErrorKind::Permission => "permission",
+            ErrorKind::Upstream => "upstream",
+            ErrorKind::Bug => "bug",
+        })
+    }
+}
+
+/// One failure, with the short ID shown to the member and written to the log
+#[derive(Debug, Clone)]
+pub struct Incident {
+    pub id: String,
+    pub kind: ErrorKind,
+    /// replaces the kind's default message
+    pub message: Option<String>,
+}
+
+impl Incident {
+    pub fn new(kind: ErrorKind) -> Self {
+        Self {
+            id: format!("{:08x}", rand::rng().random::<u32>()),
+            kind,
+            message: None,
+        }
+    }
+
+    /// Classifies the error, keeping its message only when it's the member's mistake
+    pub fn from_error(err: &Error) -> Self {
+        let kind = ErrorKind::classify(err.as_ref());
+        let incident = Self::new(kind);
+
+        match kind {
+            ErrorKind::UserInput => incident.with_message(err.to_string()),
+            _ => incident,
+        }
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    pub fn to_embed(&self) -> CreateEmbed {
+        CreateEmbed::new()
+            .title(self.kind.title())
+            .description(
+                self.message
+                    .as_deref()
+                    .unwrap_or(self.kind.default_message()),
+            )
+            .colour(self.kind.colour())
+            .footer(CreateEmbedFooter::new(format!("Incident ID: {}", self.id)))
+    }
+
+    fn log(&self, context: fmt::Arguments<'_>, details: &dyn fmt::Debug) {
+        match self.kind {
+            ErrorKind::UserInput | ErrorKind::Permission => tracing::warn!(
+                incident = %self.id,
+                kind = %self.kind,
+                "Incident {} in {}: {:?}",
+                self.id,
+                context,
+                details
+            ),
+            ErrorKind::Upstream | ErrorKind::Bug => tracing::error!(
+                incident = %self.id,
+                kind = %self.kind,
+                "Incident {} in {}: {:?}",
+                self.id,
+                context,
+                details
+            ),
+        }
+    }
+}
+
+fn record_upstream(metrics: &Metrics, incident: &Incident, error: &Error) {
+    if incident.kind == ErrorKind::Upstream
+        && let Some(service) = ErrorKind::upstream_service(error.as_ref())
+    {
+        metrics.record_upstream_error(service);
+    }
+}
+
+async fn reply_to_component(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    incident: &Incident,
+) {
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .embed(incident.to_embed())
+            .ephemeral(true),
+    );
+
+    // the handler may have already responded before failing
+    if interaction.create_response(ctx, response).await.is_ok() {
+        return;
+    }
+
+    let followup = CreateInteractionResponseFollowup::new()
+        .embed(incident.to_embed())
+        .ephemeral(true);
+
+    if let Err(err) = interaction.create_followup(ctx, followup).await {
+        tracing::warn!(incident = %incident.id, error = %err, "Failed to report incident");
+    }
+}
+
+/// Replies to the member with an incident embed and logs the full error against its ID
+pub async fn on_error(error: FrameworkError<'_, Data, Error>) {
+    let (ctx, incident) = match error {
+        FrameworkError::Command { error, ctx, .. } => {
+            let incident = Incident::from_error(&error);
+            record_upstream(&ctx.data().state.metrics, &incident, &error);
+            incident.log(
+                format_args!(
+                    "/{} by {} in {:?}",
+                    ctx.command().qualified_name,
+                    ctx.author().id,
+                    ctx.guild_id()
+                ),
+                &error,
+            );
+            (ctx, incident)
+        }
+        FrameworkError::CommandPanic { payload, ctx, .. } => {
+            let incident = Incident::new(ErrorKind::Bug);
+            incident.log(
+                format_args!("/{} (panicked)", ctx.command().qualified_name),
+                &payload,
+            );
+            (ctx, incident)
+        }
+        FrameworkError::ArgumentParse {
+            error, input, ctx, ..
+        } => {
+            let message = match input {
+                Some(input) => format!("Couldn't understand `{}`: {}", input, error),
+                None => format!("Couldn't understand an option: {}", error),
+            };
+            (
+                ctx,
+                Incident::new(ErrorKind::UserInput).with_message(message),
+            )
+        }
+        FrameworkError::CooldownHit {
+            remaining_cooldown,
+            ctx,
+            ..
+        } => (
+            ctx,
+            Incident::new(ErrorKind::UserInput).with_message(format!(
+                "Slow down, try again in {} seconds.",
+                remaining_cooldown.as_secs().max(1)
+            )),
+        ),
+        FrameworkError::MissingUserPermissions {
+            missing_permissions,
+            ctx,
+            ..
+        } => {
+            let message = match missing_permissions {
+                Some(permissions) => format!("You need the {} permission for this.", permissions),
+                None => "You don't have permission to use this command.".to_string(),
+            };
+            (
+                ctx,
+                Incident::new(ErrorKind::Permission).with_message(message),
+            )
+        }
+        FrameworkError::MissingBotPermissions {
+            missing_permissions,
+            ctx,
+            ..
+        } => (
+            ctx,
+            Incident::new(ErrorKind::Permission).with_message(format!(
+                "The bot needs the {} permission for this. Let a committee member know.",
+                missing_permissions
+            )),
+        ),
+        FrameworkError::NotAnOwner { ctx, .. } => (
+            ctx,
+            Incident::new(ErrorKind::Permission)
+                .with_message("Only the bot's owners can use this command."),
+        ),
+        FrameworkError::GuildOnly { ctx, .. } => (
+            ctx,
+            Incident::new(ErrorKind::UserInput)
+                .with_message("This command can only be used in the DSEC server."),
+        ),
+        FrameworkError::EventHandler {
+            error,
+            ctx,
+            event,
+            framework,
+            ..
+        } => {
+            let incident = Incident::from_error(&error);
+            record_upstream(&framework.user_data.state.metrics, &incident, &error);
+
+            if let serenity::FullEvent::InteractionCreate { interaction } = event
+                && let Some(interaction) = interaction.as_message_component()
+            {
+                incident.log(
+                    format_args!(
+                        "component {} by {} in {:?}",
+                        interaction.data.custom_id, interaction.user.id, interaction.guild_id
+                    ),
+                    &error,
+                );
+                reply_to_component(ctx, interaction, &incident).await;
+                return;
+            }
+
+            incident.log(format_args!("{} event", event.snake_case_name()), &error);
+            return;
+        }
+        other => {
+            if let Err(err) = poise::builtins::on_error(other).await {
+                tracing::error!(error = %err, "Failed to handle framework error");
+            }
+            return;
+        }
+    };
+
+    if let Err(err) = ctx
+        .send(
+            CreateReply::default()
+                .embed(incident.to_embed())
+                .ephemeral(true),
+        )
+        .await
+    {
+        tracing::warn!(incident = %incident.id, error = %err, "Failed to report incident");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_errors() {
+        let user: Error = UserError("bad input".to_string()).into();
+        let bug: Error = "unexpected".into();
+        let upstream: Error = serenity::Error::Http(serenity::HttpError::InvalidPort).into();
+        let permission: Error = serenity::Error::Model(serenity::ModelError::Hierarchy).into();
+
+        assert_eq!(ErrorKind::classify(user.as_ref()), ErrorKind::UserInput);
+        assert_eq!(ErrorKind::classify(bug.as_ref()), ErrorKind::Bug);
+        assert_eq!(ErrorKind::classify(upstream.as_ref()), ErrorKind::Upstream);
+        assert_eq!(
+            ErrorKind::upstream_service(upstream.as_ref()),
+            Some("discord")
+        );
+        assert_eq!(
+            ErrorKind::classify(permission.as_ref()),
+            ErrorKind::Permission
+        );
+    }
+
+    #[test]
+    fn only_shows_messages_of_user_errors() {
+        let user = Incident::from_error(&UserError("bad input".to_string()).into());
+        let bug = Incident::from_error(&"secret details".into());
+
+        assert_eq!(user.message.as_deref(), Some("bad input"));
+        assert_eq!(bug.message, None);
+        assert_eq!(bug.id.len(), 8);
+    }
+}
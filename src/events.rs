@@ -1,2 +1,3 @@
 pub mod ready;
-pub mod interaction_create;
\ No newline at end of file
+pub mod interaction_create;
+pub mod guild_member_addition;
\ No newline at end of file
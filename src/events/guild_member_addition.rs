@@ -0,0 +1,60 @@
+use crate::{
+    Data, Error,
+    commands::verification::StudentRow,
+    verification::audit::{AuditRecord, VerificationOutcome, VerificationPath},
+};
+use poise::serenity_prelude as serenity;
+
+/// Gives a rejoining member their verified role back if their linked membership is still active
+pub async fn on_guild_member_addition(
+    ctx: &serenity::Context,
+    new_member: &serenity::Member,
+    data: &Data,
+) -> Result<(), Error> {
+    let state = &data.state;
+
+    if new_member.user.bot {
+        return Ok(());
+    }
+
+    // the verified role only exists in the club's server
+    if let Some(guild_id) = data.config.guild_id
+        && guild_id != new_member.guild_id
+    {
+        return Ok(());
+    }
+
+    let Some(link) = state.links.find_by_user(new_member.user.id).await? else {
+        return Ok(());
+    };
+
+    let record = state.directory.find_member(&link.student_id).await?;
+
+    // a lapsed membership has to verify again once renewed
+    let Some(record) = record.filter(StudentRow::is_current) else {
+        return Ok(());
+    };
+
+    new_member
+        .add_role(ctx, data.config.verified_role_id)
+        .await?;
+    // the verified role is already back, so a failed tier sync shouldn't skip the audit record
+    if let Err(err) = state.tiers.sync(ctx, new_member, Some(&record)).await {
+        tracing::error!(user = new_member.user.id.get(), error = %err, "Failed to sync tier roles");
+    }
+
+    state
+        .audit
+        .record(
+            ctx,
+            AuditRecord::new(
+                &new_member.user,
+                &link.student_id,
+                VerificationOutcome::Restored,
+                VerificationPath::Automatic,
+            ),
+        )
+        .await;
+
+    Ok(())
+}
@@ -2,14 +2,31 @@ use std::time::Duration;
 
 use crate::{
     Data, Error,
-    commands::verification::{StudentRow, VerificationModal},
+    commands::verification::{CodeModal, CourseModal, EmailModal, StudentRow, VerificationModal},
+    verification::{
+        audit::{AuditRecord, VerificationOutcome, VerificationPath},
+        cache::CacheLookup,
+        email::{self, CodeCheck},
+        import::{self, ImportDecision},
+        onboarding::{
+            CAMPUS_SELECT_ID, COURSE_BUTTON_ID, Campus, MemberProfile, OnboardingChoice,
+            YEAR_SELECT_ID, YearLevel,
+        },
+        panel,
+        privacy::{self, ERASE_CANCEL_ID, ERASE_CONFIRM_ID},
+        review::{self, REVIEW_REQUEST_ID, ReviewAttempt, ReviewButton, ReviewDecision},
+        student_id::StudentId,
+        throttle::Throttled,
+    },
 };
 use ::serenity::all::{
-    ComponentInteraction, Context, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
-    CreateInteractionResponseFollowup, CreateInteractionResponseMessage, GuildId, RoleId,
+    ButtonStyle, Colour, ComponentInteraction, ComponentInteractionDataKind, Context,
+    CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+    CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateMessage,
+    EditInteractionResponse, GuildId, Member, RoleId, Timestamp, UserId,
 };
-use dotenv::dotenv;
 use poise::{modal, serenity_prelude as serenity};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 struct ContextRef<'a>(&'a Context);
 impl AsRef<Context> for ContextRef<'_> {
@@ -54,167 +71,1230 @@ async fn embed_followup(
     Ok(())
 }
 
-pub async fn on_interaction_create(
+// same message for "ID not found" and "name mismatch", so the modal can't be used
+// to find out which student IDs are in active_members
+const VERIFICATION_FAILED_TITLE: &str = "Verification failed ❌";
+const VERIFICATION_FAILED_DESCRIPTION: &str = "We couldn't match that full name and student ID to an active DSEC membership. Double-check both and try again.
+
+If you signed up recently, it can take up to **a week** for your membership to be added to the database.";
+
+const REVIEW_OFFER: &str = "
+
+If your details are correct (for example, you go by a preferred name), you can ask the committee to review your attempt.";
+
+const EMAIL_OFFER: &str = "
+
+Not in the member list yet? You can also verify with your Deakin email address.";
+
+const EMAIL_ALREADY_LINKED: &str = "This email address has already been used to verify another Discord account. If this is your address, please contact the committee.";
+
+/// Failure followup for an attempt, with "Request manual review" and "Use Deakin email" buttons
+/// when those are enabled
+fn failure_followup(
+    data: &Data,
+    user_id: UserId,
+    attempt: ReviewAttempt,
+    title: &str,
+    description: &str,
+) -> CreateInteractionResponseFollowup {
+    let state = &data.state;
+    let mut description = description.to_string();
+    let mut buttons = Vec::new();
+
+    if state.email.is_enabled() {
+        description.push_str(EMAIL_OFFER);
+        buttons.push(
+            CreateButton::new(email::button_id(attempt.role_id))
+                .label(panel::EMAIL_BUTTON_LABEL)
+                .style(ButtonStyle::Secondary),
+        );
+    }
+
+    if state.reviews.is_enabled() {
+        description.push_str(REVIEW_OFFER);
+        buttons.push(
+            CreateButton::new(REVIEW_REQUEST_ID)
+                .label("Request manual review")
+                .style(ButtonStyle::Secondary),
+        );
+
+        state.reviews.remember(user_id, attempt);
+    }
+
+    let embed = CreateEmbed::new().title(title).description(description);
+    let mut followup = CreateInteractionResponseFollowup::new()
+        .add_embed(embed)
+        .ephemeral(true);
+
+    if !buttons.is_empty() {
+        followup = followup.components(vec![CreateActionRow::Buttons(buttons)]);
+    }
+
+    followup
+}
+
+fn throttled_message(throttled: Throttled) -> (&'static str, String) {
+    let retry_at = SystemTime::now() + throttled.retry_after();
+    let retry_at_unix = retry_at
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    match throttled {
+        Throttled::User { .. } => (
+            "Too many attempts ⏳",
+            format!(
+                "You have made too many failed verification attempts. Try again <t:{}:R>.",
+                retry_at_unix
+            ),
+        ),
+        Throttled::Guild { .. } => (
+            "Verification paused ⏳",
+            format!(
+                "Verification is receiving too many failed attempts right now. Try again <t:{}:R>.",
+                retry_at_unix
+            ),
+        ),
+    }
+}
+
+/// Links the student ID to the member and grants the panel's role, once the name and ID have matched
+#[allow(clippy::too_many_arguments)]
+async fn complete_verification(
     ctx: &serenity::Context,
-    interaction: &serenity::Interaction,
+    interaction: &ComponentInteraction,
     data: &Data,
+    discord_member: &Member,
+    record: &StudentRow,
+    submitted_name: &str,
+    panel_role: Option<RoleId>,
+    path: VerificationPath,
 ) -> Result<(), Error> {
-    let Some(interaction) = interaction.as_message_component() else {
+    let state = &data.state;
+    let student_id = record.student_id.as_str();
+    let user_id = interaction.user.id;
+    let verified_role_id = panel_role.unwrap_or(data.config.verified_role_id);
+
+    // each student ID can only verify one Discord account, so linking fails if another holds it
+    if state.links.link(student_id, user_id).await?.is_none() {
+        let holder = state.links.find_by_student(student_id).await?;
+
+        if let Some(guild_id) = interaction.guild_id {
+            state.throttle.record_failure(guild_id, user_id);
+        }
+
+        state
+            .audit
+            .record(
+                ctx,
+                AuditRecord::new(
+                    &interaction.user,
+                    student_id,
+                    VerificationOutcome::AlreadyLinked,
+                    path,
+                )
+                .with_details(match holder {
+                    Some(link) => format!("Already linked to <@{}>", link.discord_user_id),
+                    None => "Already linked to another account".to_string(),
+                }),
+            )
+            .await;
+
+        let attempt = ReviewAttempt::new(
+            student_id,
+            submitted_name,
+            VerificationOutcome::AlreadyLinked,
+            Some(record.full_name.clone()),
+            panel_role,
+        );
+
+        let followup = failure_followup(
+            data,
+            user_id,
+            attempt,
+            "Already linked ❌",
+            "This student ID has already been used to verify another Discord account. If this is your student ID, please contact the committee.",
+        );
+
+        interaction.create_followup(ctx, followup).await?;
+
         return Ok(());
+    }
+
+    discord_member.add_role(ctx, verified_role_id).await?;
+    // the verified role is already granted, so a failed tier sync shouldn't fail verification
+    if let Err(err) = state.tiers.sync(ctx, discord_member, Some(record)).await {
+        tracing::error!(user = user_id.get(), error = %err, "Failed to sync tier roles");
+    }
+    state.throttle.record_success(user_id);
+
+    state
+        .audit
+        .record(
+            ctx,
+            AuditRecord::new(
+                &interaction.user,
+                student_id,
+                VerificationOutcome::Verified,
+                path,
+            ),
+        )
+        .await;
+
+    let mut verified_embed = CreateEmbed::new().title("Verified ✅").description(format!(
+        "You have been assigned the <@&{}> role!",
+        verified_role_id
+    ));
+
+    if path == VerificationPath::Cache {
+        verified_embed = verified_embed.footer(CreateEmbedFooter::new("⚡ via cache"));
+    }
+
+    let verified_msg = CreateInteractionResponseFollowup::new()
+        .add_embed(verified_embed)
+        .ephemeral(true);
+
+    interaction.create_followup(ctx, verified_msg).await?;
+
+    if state.onboarding.config().enabled {
+        send_onboarding(ctx, interaction, data, student_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Asks a newly verified member for their course, campus and year level
+async fn send_onboarding(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    data: &Data,
+    student_id: &str,
+) -> Result<(), Error> {
+    let onboarding = &data.state.onboarding;
+    let user_id = interaction.user.id;
+
+    // the member is already verified, a missing profile table shouldn't look like a failure
+    let profile = match onboarding.find(user_id).await {
+        Ok(profile) => profile.unwrap_or_else(|| MemberProfile::new(user_id, student_id)),
+        Err(err) => {
+            tracing::error!(user = user_id.get(), error = %err, "Failed to load member profile");
+            return Ok(());
+        }
+    };
+
+    let (embed, components) = onboarding.message(&profile);
+
+    let followup = CreateInteractionResponseFollowup::new()
+        .add_embed(embed)
+        .components(components)
+        .ephemeral(true);
+
+    interaction.create_followup(ctx, followup).await?;
+
+    Ok(())
+}
+
+/// Saves an answer from the onboarding message and gives the matching role
+async fn handle_onboarding(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    data: &Data,
+) -> Result<(), Error> {
+    let state = &data.state;
+    let onboarding = &state.onboarding;
+    let config = onboarding.config();
+    let user_id = interaction.user.id;
+
+    let Some(link) = state.links.find_by_user(user_id).await? else {
+        embed_response(
+            ctx,
+            interaction,
+            "Verify first",
+            "Click **Verify Here** to verify your membership before adding your details.",
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    let mut profile = onboarding
+        .find(user_id)
+        .await?
+        .unwrap_or_else(|| MemberProfile::new(user_id, &link.student_id));
+    profile.student_id = link.student_id;
+    profile.updated_at = Timestamp::now();
+
+    let selected = match &interaction.data.kind {
+        ComponentInteractionDataKind::StringSelect { values } => values.first().map(String::as_str),
+        _ => None,
     };
 
-    if interaction.data.custom_id == "verify" {
-        dotenv().ok();
-        let guild_id = match interaction.guild_id {
-            Some(id) => id,
-            None => {
-                // user not in a server at all
+    match interaction.data.custom_id.as_str() {
+        COURSE_BUTTON_ID => {
+            let modal_data = modal::execute_modal_on_component_interaction::<CourseModal>(
+                ContextRef(ctx),
+                interaction.clone(),
+                None,
+                Some(Duration::from_secs(120)),
+            )
+            .await?;
+
+            let Some(modal_data) = modal_data else {
+                return Ok(());
+            };
+
+            profile.course =
+                Some(modal_data.course.trim().to_string()).filter(|course| !course.is_empty());
+            onboarding.save(&profile).await?;
+
+            embed_followup(
+                ctx,
+                interaction,
+                "Course saved ✅",
+                format!(
+                    "Your course is now **{}**.",
+                    profile.course.as_deref().unwrap_or("not set")
+                ),
+            )
+            .await?;
+
+            return Ok(());
+        }
+        CAMPUS_SELECT_ID => {
+            let Some(campus) = selected.and_then(Campus::from_key) else {
+                return Ok(());
+            };
+
+            profile.campus = Some(campus);
+
+            if let Some(member) = &interaction.member {
+                config
+                    .campus_roles
+                    .sync(ctx, member, config.campus_roles.get(campus.key()))
+                    .await?;
+            }
+        }
+        YEAR_SELECT_ID => {
+            let Some(year_level) = selected.and_then(YearLevel::from_key) else {
+                return Ok(());
+            };
+
+            profile.year_level = Some(year_level);
+
+            if let Some(member) = &interaction.member {
+                config
+                    .year_roles
+                    .sync(ctx, member, config.year_roles.get(year_level.key()))
+                    .await?;
+            }
+        }
+        _ => return Ok(()),
+    }
+
+    onboarding.save(&profile).await?;
+
+    let (embed, components) = onboarding.message(&profile);
+
+    let response = CreateInteractionResponse::UpdateMessage(
+        CreateInteractionResponseMessage::new()
+            .embeds(vec![embed])
+            .components(components),
+    );
+
+    interaction.create_response(ctx, response).await?;
+
+    Ok(())
+}
+
+/// Sends the member's last failed attempt to the committee review channel
+async fn handle_review_request(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    data: &Data,
+) -> Result<(), Error> {
+    let state = &data.state;
+
+    let Some(attempt) = state.reviews.take(interaction.user.id) else {
+        embed_response(
+            ctx,
+            interaction,
+            "Review unavailable",
+            "This attempt has expired or was already submitted. Click **Verify Here** to try again.",
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    state
+        .reviews
+        .submit(ctx, &interaction.user, &attempt)
+        .await?;
+
+    state
+        .audit
+        .record(
+            ctx,
+            AuditRecord::new(
+                &interaction.user,
+                &attempt.student_id,
+                VerificationOutcome::ReviewRequested,
+                VerificationPath::Manual,
+            )
+            .with_details(format!("Submitted name: {}", attempt.submitted_name)),
+        )
+        .await;
+
+    let embed = CreateEmbed::new().title("Review requested 📝").description(
+        "The committee will review your attempt. You'll get a DM once they have made a decision.",
+    );
+
+    // replace the failure message so the button can't be pressed twice
+    let response = CreateInteractionResponse::UpdateMessage(
+        CreateInteractionResponseMessage::new()
+            .embeds(vec![embed])
+            .components(vec![]),
+    );
+
+    interaction.create_response(ctx, response).await?;
+
+    Ok(())
+}
+
+/// Applies a committee member's Approve/Reject decision from the review channel
+async fn handle_review_decision(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    data: &Data,
+    button: ReviewButton,
+) -> Result<(), Error> {
+    let state = &data.state;
+    let ReviewButton {
+        decision,
+        user_id,
+        student_id,
+        role_id,
+    } = button;
+    let student_id = student_id.as_str();
+
+    let Some(guild_id) = interaction.guild_id else {
+        return Ok(());
+    };
+
+    let is_moderator = interaction
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.manage_messages());
+
+    if !is_moderator {
+        embed_response(
+            ctx,
+            interaction,
+            "Unable to perform action",
+            "Only committee members can review verifications.",
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let reviewer_id = interaction.user.id;
+
+    let (user, outcome, decision_text, colour, dm_embed) = match decision {
+        ReviewDecision::Approve => {
+            let Ok(member) = guild_id.member(ctx, user_id).await else {
+                embed_response(
+                    ctx,
+                    interaction,
+                    "Member not found",
+                    "This member is no longer in the server.",
+                )
+                .await?;
+
+                return Ok(());
+            };
+
+            let verified_role_id = role_id.unwrap_or(data.config.verified_role_id);
+
+            if state.links.link(student_id, user_id).await?.is_none() {
                 embed_response(
                     ctx,
                     interaction,
-                    "Unable to perform action",
-                    "Action can only be performed in the DSEC server",
+                    "Already linked",
+                    "This student ID is linked to another Discord account. Move it with `/link transfer`, which also removes the other account's roles, instead of approving.",
                 )
                 .await?;
 
                 return Ok(());
             }
-        };
 
-        let role_id_string = std::env::var("VERIFIED_ROLE_ID").expect("missing VERIFIED_ROLE_ID");
+            member.add_role(ctx, verified_role_id).await?;
+
+            let record = state.directory.find_member(student_id).await?;
+            if let Err(err) = state.tiers.sync(ctx, &member, record.as_ref()).await {
+                tracing::error!(user = user_id.get(), error = %err, "Failed to sync tier roles");
+            }
+            state.throttle.record_success(user_id);
 
-        let role_id_u64: u64 = role_id_string
-            .parse()
-            .expect("Unable to parse VERIFIED_ROLE_ID into number");
+            let dm_embed = CreateEmbed::new()
+                .title("Verification approved ✅")
+                .description("The committee approved your DSEC membership verification. You now have the verified role!")
+                .colour(Colour::DARK_GREEN);
 
-        let user_id = interaction.user.id;
-        let verified_role_id = RoleId::new(role_id_u64);
+            (
+                member.user,
+                VerificationOutcome::ReviewApproved,
+                format!("Approved by <@{}>", reviewer_id),
+                Colour::DARK_GREEN,
+                dm_embed,
+            )
+        }
+        ReviewDecision::Reject => {
+            let user = user_id.to_user(ctx).await?;
 
-        let discord_member = GuildId::member(guild_id, ctx, user_id).await?;
-        let has_role = discord_member.roles.contains(&verified_role_id);
+            let dm_embed = CreateEmbed::new()
+                .title("Verification not approved ❌")
+                .description("The committee could not match your details to a DSEC membership. Please contact the committee if you think this is a mistake.")
+                .colour(Colour::RED);
 
-        // Has role
-        if has_role {
-            embed_response(
+            (
+                user,
+                VerificationOutcome::ReviewRejected,
+                format!("Rejected by <@{}>", reviewer_id),
+                Colour::RED,
+                dm_embed,
+            )
+        }
+    };
+
+    state
+        .audit
+        .record(
+            ctx,
+            AuditRecord::new(&user, student_id, outcome, VerificationPath::Manual)
+                .with_details(decision_text.clone()),
+        )
+        .await;
+
+    let review_embed = match interaction.message.embeds.first() {
+        Some(embed) => CreateEmbed::from(embed.clone()),
+        None => CreateEmbed::new().title("Verification review 📝"),
+    }
+    .field("Decision", decision_text, false)
+    .colour(colour);
+
+    let response = CreateInteractionResponse::UpdateMessage(
+        CreateInteractionResponseMessage::new()
+            .embeds(vec![review_embed])
+            .components(vec![]),
+    );
+
+    interaction.create_response(ctx, response).await?;
+
+    if let Err(err) = user
+        .direct_message(ctx, CreateMessage::new().embed(dm_embed))
+        .await
+    {
+        tracing::warn!(user = user.id.get(), error = %err, "Failed to DM member about their review");
+    }
+
+    Ok(())
+}
+
+/// Applies or discards a previewed membership import
+async fn handle_import_decision(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    data: &Data,
+    decision: ImportDecision,
+    import_id: u64,
+) -> Result<(), Error> {
+    let state = &data.state;
+
+    let is_moderator = interaction
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.manage_messages());
+
+    if !is_moderator {
+        embed_response(
+            ctx,
+            interaction,
+            "Unable to perform action",
+            "Only committee members can import members.",
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let Some(pending) = state.imports.take(import_id, interaction.user.id) else {
+        let response = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content("This import has expired or was already handled. Upload the file again.")
+                .embeds(vec![])
+                .components(vec![]),
+        );
+
+        interaction.create_response(ctx, response).await?;
+
+        return Ok(());
+    };
+
+    if decision == ImportDecision::Cancel {
+        let response = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content("Import cancelled, nothing was changed.")
+                .embeds(vec![])
+                .components(vec![]),
+        );
+
+        interaction.create_response(ctx, response).await?;
+
+        return Ok(());
+    }
+
+    // large imports take a while, acknowledge before writing
+    interaction.defer(ctx).await?;
+
+    let diff = pending.diff;
+
+    state.directory.upsert_members(&diff.upserts()).await?;
+    state.directory.remove_members(&diff.removals()).await?;
+
+    for student_id in diff.affected_ids() {
+        state.student_cache.invalidate(&student_id);
+    }
+
+    // the members are already written, the sync record only feeds /membership
+    if let Err(err) = state.directory.record_sync(&diff.to_sync_record()).await {
+        tracing::error!(error = %err, "Failed to record membership import");
+    }
+
+    let embed = diff.to_applied_embed(pending.author);
+    state.audit.notify(ctx, embed.clone()).await;
+
+    interaction
+        .edit_response(
+            ctx,
+            EditInteractionResponse::new()
+                .embed(embed)
+                .components(vec![]),
+        )
+        .await?;
+
+    Ok(())
+}
+
+async fn handle_email_verify(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    data: &Data,
+    panel_role: Option<RoleId>,
+) -> Result<(), Error> {
+    let state = &data.state;
+
+    let Some(guild_id) = interaction.guild_id else {
+        embed_response(
+            ctx,
+            interaction,
+            "Unable to perform action",
+            "Action can only be performed in the DSEC server",
+        )
+        .await?;
+
+        return Ok(());
+    };
+
+    // a panel posted while email verification was on can outlive it
+    if !state.email.is_enabled() {
+        embed_response(
+            ctx,
+            interaction,
+            "Email verification is off",
+            "Use the **Verify Here** button with your student ID instead.",
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let user_id = interaction.user.id;
+    let verified_role_id = panel_role.unwrap_or(data.config.verified_role_id);
+
+    let discord_member = GuildId::member(guild_id, ctx, user_id).await?;
+
+    if discord_member.roles.contains(&verified_role_id) {
+        embed_response(
+            ctx,
+            interaction,
+            "Already Verified ✅",
+            format!("You already have the <@&{}> role!", verified_role_id),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    if let Err(throttled) = state.throttle.check(guild_id, user_id) {
+        let (title, description) = throttled_message(throttled);
+        embed_response(ctx, interaction, title, description).await?;
+
+        return Ok(());
+    }
+
+    let modal_data = modal::execute_modal_on_component_interaction::<EmailModal>(
+        ContextRef(ctx),
+        interaction.clone(),
+        None,
+        Some(Duration::from_secs(120)),
+    )
+    .await?;
+
+    let Some(modal_data) = modal_data else {
+        return Ok(());
+    };
+
+    let address = match email::parse_address(&modal_data.email, &state.email.config().domain) {
+        Ok(address) => address,
+        Err(err) => {
+            embed_followup(
                 ctx,
                 interaction,
-                "Already Verified ✅",
-                format!("You already have the <@&{}> role!", verified_role_id),
+                "Invalid email address ❌",
+                err.to_string(),
             )
             .await?;
 
             return Ok(());
         }
+    };
 
-        // modal
-        let timeout = Duration::from_secs(120);
-
-        let modal_data = modal::execute_modal_on_component_interaction::<VerificationModal>(
-            ContextRef(ctx),
-            interaction.clone(),
-            None,
-            Some(timeout),
+    // checked again when the code is entered, this only avoids sending a code that can't work
+    if let Some(link) = state
+        .email_links
+        .find_by_email(&email::mailbox(&address))
+        .await?
+        && link.discord_user_id != user_id
+    {
+        embed_followup(
+            ctx,
+            interaction,
+            "Email already used ❌",
+            EMAIL_ALREADY_LINKED,
         )
         .await?;
 
-        let modal_data = match modal_data {
-            Some(data) => data,
-            None => return Ok(()),
-        };
+        return Ok(());
+    }
 
-        let input_student_id: &str = &modal_data.student_id.to_lowercase();
-        let student_id = input_student_id
-            .strip_prefix("s")
-            .unwrap_or(input_student_id);
+    let code = match state.email.issue(user_id, &address, panel_role) {
+        Ok(code) => code,
+        Err(retry_after) => {
+            embed_followup(
+                ctx,
+                interaction,
+                "Code already sent ⏳",
+                format!(
+                    "Check your inbox for the code we just sent, or ask for a new one in {} seconds.",
+                    retry_after.as_secs().max(1)
+                ),
+            )
+            .await?;
 
-        let state = &data.state;
+            return Ok(());
+        }
+    };
 
-        let student_in_cache: bool = {
-            let cache: std::sync::MutexGuard<'_, std::collections::HashMap<String, String>> =
-                state.student_cache.lock().expect("Failed to get cache");
+    if let Err(err) = state.email.send(&address, &code).await {
+        tracing::error!(user = user_id.get(), error = %err, "Failed to send verification code");
+        state.email.cancel(user_id);
 
-            match cache.get(student_id) {
-                Some(cached_name) => cached_name == &modal_data.name.to_lowercase(),
-                None => false,
-            }
-        };
+        embed_followup(
+            ctx,
+            interaction,
+            "Couldn't send the email ❌",
+            "Something went wrong sending your code. Try again later or ask the committee for help.",
+        )
+        .await?;
 
-        if student_in_cache {
-            discord_member.add_role(ctx, verified_role_id).await?;
+        return Ok(());
+    }
 
-            let verified_cache_embed = CreateEmbed::new()
-                .title("Verified ✅")
-                .description(format!(
-                    "You have been assigned the <@&{}> role!",
-                    verified_role_id
-                ))
-                .footer(CreateEmbedFooter::new("⚡ via cache"));
+    let expires_at = SystemTime::now() + state.email.config().code_ttl;
+    let expires_at_unix = expires_at
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
 
-            let verified_msg = CreateInteractionResponseFollowup::new()
-                .add_embed(verified_cache_embed)
-                .ephemeral(true);
+    let embed = CreateEmbed::new()
+        .title("Check your email ✉️")
+        .description(format!(
+            "We sent a 6-digit code to **{}**. It expires <t:{}:R>.\n\nClick **Enter code** once it arrives. It may take a minute, and can end up in your junk folder.",
+            address, expires_at_unix
+        ));
 
-            interaction.create_followup(ctx, verified_msg).await?;
+    let button = CreateButton::new(email::CODE_BUTTON_ID).label("Enter code");
 
-            return Ok(());
-        }
+    interaction
+        .create_followup(
+            ctx,
+            CreateInteractionResponseFollowup::new()
+                .add_embed(embed)
+                .components(vec![CreateActionRow::Buttons(vec![button])])
+                .ephemeral(true),
+        )
+        .await?;
 
-        // fetch from DB
-        let student_data: Vec<StudentRow> = state
-            .supabase
-            .database()
-            .from("active_members")
-            .select("full_name, student_id")
-            .eq("student_id", &student_id)
-            .execute()
-            .await?;
+    Ok(())
+}
 
-        let result = student_data.iter().next();
+async fn handle_email_code(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    data: &Data,
+) -> Result<(), Error> {
+    let state = &data.state;
 
-        if result.is_none() {
-            // TODO: add user to "don't use this command for 5 minutes"
+    let Some(guild_id) = interaction.guild_id else {
+        return Ok(());
+    };
 
-            embed_followup(ctx, 
-                interaction, 
-                "Student ID not found!", 
-                "Your student ID is not found. 
-                It takes up to **a week** for your membership to be updated in the database since sign up. 
-                Try again later.").await?;
+    let user_id = interaction.user.id;
 
-            return Ok(());
-        }
+    let modal_data = modal::execute_modal_on_component_interaction::<CodeModal>(
+        ContextRef(ctx),
+        interaction.clone(),
+        None,
+        Some(Duration::from_secs(300)),
+    )
+    .await?;
 
-        // get name from result
-        let result_name = &result.unwrap().full_name;
+    let Some(modal_data) = modal_data else {
+        return Ok(());
+    };
 
-        {
-            let mut cache = state.student_cache.lock().unwrap();
-            cache.insert(
-                student_id.to_string(),
-                result_name.to_string().to_lowercase(),
-            );
-        }
+    match state.email.check(user_id, &modal_data.code) {
+        CodeCheck::Verified { email, role_id } => {
+            let verified_role_id = role_id.unwrap_or(data.config.verified_role_id);
+            let discord_member = GuildId::member(guild_id, ctx, user_id).await?;
+
+            // each address can only verify one Discord account
+            if state
+                .email_links
+                .link(&email::mailbox(&email), user_id)
+                .await?
+                .is_none()
+            {
+                state.throttle.record_failure(guild_id, user_id);
+
+                state
+                    .audit
+                    .record(
+                        ctx,
+                        AuditRecord::new(
+                            &interaction.user,
+                            "",
+                            VerificationOutcome::AlreadyLinked,
+                            VerificationPath::Email,
+                        )
+                        .with_details(format!("Email: {}", email)),
+                    )
+                    .await;
+
+                embed_followup(
+                    ctx,
+                    interaction,
+                    "Email already used ❌",
+                    EMAIL_ALREADY_LINKED,
+                )
+                .await?;
+
+                return Ok(());
+            }
 
-        if &result_name.to_lowercase() == &modal_data.name.to_lowercase() {
             discord_member.add_role(ctx, verified_role_id).await?;
+            state.throttle.record_success(user_id);
+
+            state
+                .audit
+                .record(
+                    ctx,
+                    AuditRecord::new(
+                        &interaction.user,
+                        "",
+                        VerificationOutcome::EmailVerified,
+                        VerificationPath::Email,
+                    )
+                    .with_details(format!("Email: {}", email)),
+                )
+                .await;
 
             embed_followup(
                 ctx,
                 interaction,
                 "Verified ✅",
-                format!("You have been assigned the <@&{}> role!", verified_role_id),
+                format!("You now have the <@&{}> role!", verified_role_id),
+            )
+            .await?;
+        }
+        CodeCheck::Wrong { remaining } => {
+            embed_followup(
+                ctx,
+                interaction,
+                "Wrong code ❌",
+                format!(
+                    "That code doesn't match. You have {} more {} before it stops working.",
+                    remaining,
+                    if remaining == 1 { "try" } else { "tries" }
+                ),
+            )
+            .await?;
+        }
+        CodeCheck::TooManyAttempts { email } => {
+            state.throttle.record_failure(guild_id, user_id);
+
+            state
+                .audit
+                .record(
+                    ctx,
+                    AuditRecord::new(
+                        &interaction.user,
+                        "",
+                        VerificationOutcome::EmailCodeFailed,
+                        VerificationPath::Email,
+                    )
+                    .with_details(format!("Email: {}", email)),
+                )
+                .await;
+
+            embed_followup(
+                ctx,
+                interaction,
+                "Too many wrong codes ❌",
+                "That code no longer works. Click **Use Deakin email** again to get a new one.",
             )
             .await?;
-        } else {
+        }
+        CodeCheck::Expired | CodeCheck::Missing => {
             embed_followup(
                 ctx,
                 interaction,
-                "Name mismatch ❌",
-                "Your student ID is present, however the name does not match. Try again.",
+                "Code expired ⌛",
+                "That code has expired or was already used. Click **Use Deakin email** again to get a new one.",
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn handle_privacy_erase(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    data: &Data,
+) -> Result<(), Error> {
+    if interaction.data.custom_id == ERASE_CANCEL_ID {
+        let response = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content("Cancelled, nothing was deleted.")
+                .embeds(vec![])
+                .components(vec![]),
+        );
+
+        interaction.create_response(ctx, response).await?;
+
+        return Ok(());
+    }
+
+    interaction.defer(ctx).await?;
+
+    let state = &data.state;
+    let erasure = privacy::erase(ctx, state, interaction.guild_id, interaction.user.id).await;
+
+    state
+        .audit
+        .notify(ctx, erasure.to_embed(&interaction.user))
+        .await;
+
+    interaction
+        .edit_response(
+            ctx,
+            EditInteractionResponse::new()
+                .embed(erasure.to_reply_embed())
+                .components(vec![]),
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub async fn on_interaction_create(
+    ctx: &serenity::Context,
+    interaction: &serenity::Interaction,
+    data: &Data,
+) -> Result<(), Error> {
+    let Some(interaction) = interaction.as_message_component() else {
+        return Ok(());
+    };
+
+    let custom_id = interaction.data.custom_id.as_str();
+
+    if custom_id == REVIEW_REQUEST_ID {
+        return handle_review_request(ctx, interaction, data).await;
+    }
+
+    if matches!(
+        custom_id,
+        CAMPUS_SELECT_ID | YEAR_SELECT_ID | COURSE_BUTTON_ID
+    ) {
+        return handle_onboarding(ctx, interaction, data).await;
+    }
+
+    if let Some(panel_role) = email::parse_button_id(custom_id) {
+        return handle_email_verify(ctx, interaction, data, panel_role).await;
+    }
+
+    if custom_id == email::CODE_BUTTON_ID {
+        return handle_email_code(ctx, interaction, data).await;
+    }
+
+    if matches!(custom_id, ERASE_CONFIRM_ID | ERASE_CANCEL_ID) {
+        return handle_privacy_erase(ctx, interaction, data).await;
+    }
+
+    if let Some(panel_role) = panel::parse_button_id(custom_id) {
+        return handle_verify(ctx, interaction, data, panel_role).await;
+    }
+
+    if let Some((decision, import_id)) = import::parse_button(custom_id) {
+        return handle_import_decision(ctx, interaction, data, decision, import_id).await;
+    }
+
+    match review::parse_decision(custom_id) {
+        Some(button) => handle_review_decision(ctx, interaction, data, button).await,
+        None => Ok(()),
+    }
+}
+
+async fn handle_verify(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    data: &Data,
+    panel_role: Option<RoleId>,
+) -> Result<(), Error> {
+    let guild_id = match interaction.guild_id {
+        Some(id) => id,
+        None => {
+            // user not in a server at all
+            embed_response(
+                ctx,
+                interaction,
+                "Unable to perform action",
+                "Action can only be performed in the DSEC server",
             )
             .await?;
+
+            return Ok(());
+        }
+    };
+
+    let user_id = interaction.user.id;
+    let verified_role_id = panel_role.unwrap_or(data.config.verified_role_id);
+
+    let discord_member = GuildId::member(guild_id, ctx, user_id).await?;
+    let has_role = discord_member.roles.contains(&verified_role_id);
+
+    // Has role
+    if has_role {
+        embed_response(
+            ctx,
+            interaction,
+            "Already Verified ✅",
+            format!("You already have the <@&{}> role!", verified_role_id),
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let state = &data.state;
+
+    if let Err(throttled) = state.throttle.check(guild_id, user_id) {
+        let (title, description) = throttled_message(throttled);
+        embed_response(ctx, interaction, title, description).await?;
+
+        return Ok(());
+    }
+
+    // modal
+    let timeout = Duration::from_secs(120);
+
+    let modal_data = modal::execute_modal_on_component_interaction::<VerificationModal>(
+        ContextRef(ctx),
+        interaction.clone(),
+        None,
+        Some(timeout),
+    )
+    .await?;
+
+    let modal_data = match modal_data {
+        Some(data) => data,
+        None => return Ok(()),
+    };
+
+    // the lockout may have started while this modal was open
+    if let Err(throttled) = state.throttle.check(guild_id, user_id) {
+        let (title, description) = throttled_message(throttled);
+        embed_followup(ctx, interaction, title, description).await?;
+
+        return Ok(());
+    }
+
+    // malformed IDs can't be members, so they're rejected before any lookup or throttling
+    let student_id = match modal_data.student_id.parse::<StudentId>() {
+        Ok(student_id) => student_id,
+        Err(err) => {
+            embed_followup(ctx, interaction, "Invalid student ID ❌", err.to_string()).await?;
+
+            return Ok(());
         }
+    };
+    let student_id = student_id.as_str();
+
+    let cached = state.student_cache.get(student_id);
+
+    if let CacheLookup::Found(cached_member) = &cached
+        && cached_member.is_current()
+        && state
+            .names
+            .matches(&modal_data.name, &cached_member.full_name)
+    {
+        return complete_verification(
+            ctx,
+            interaction,
+            data,
+            &discord_member,
+            cached_member,
+            &modal_data.name,
+            panel_role,
+            VerificationPath::Cache,
+        )
+        .await;
     }
 
+    // a cached name that doesn't match may be out of date, so only a cached "not found" skips the DB
+    let (member, path) = if cached == CacheLookup::NotFound {
+        (None, VerificationPath::Cache)
+    } else {
+        let member = state.directory.find_member(student_id).await?;
+
+        (member, VerificationPath::Database)
+    };
+
+    match &member {
+        Some(member) => state.student_cache.insert(member.clone()),
+        None if path == VerificationPath::Database => {
+            state.student_cache.insert_not_found(student_id)
+        }
+        None => {}
+    }
+
+    let expires_at = member
+        .as_ref()
+        .filter(|member| !member.is_current())
+        .and_then(|member| member.expires_at);
+
+    // an expired membership fails the same way as an unknown student ID
+    let Some(result) = member.filter(StudentRow::is_current) else {
+        state.throttle.record_failure(guild_id, user_id);
+
+        let mut record = AuditRecord::new(
+            &interaction.user,
+            student_id,
+            VerificationOutcome::NotFound,
+            path,
+        );
+
+        if let Some(expires_at) = expires_at {
+            record = record.with_details(format!("Membership expired on {}", expires_at));
+        }
+
+        state.audit.record(ctx, record).await;
+
+        let attempt = ReviewAttempt::new(
+            student_id,
+            &modal_data.name,
+            VerificationOutcome::NotFound,
+            None,
+            panel_role,
+        );
+
+        let followup = failure_followup(
+            data,
+            user_id,
+            attempt,
+            VERIFICATION_FAILED_TITLE,
+            VERIFICATION_FAILED_DESCRIPTION,
+        );
+
+        interaction.create_followup(ctx, followup).await?;
+
+        return Ok(());
+    };
+
+    // get name from result
+    let result_name = &result.full_name;
+
+    if state.names.matches(&modal_data.name, result_name) {
+        return complete_verification(
+            ctx,
+            interaction,
+            data,
+            &discord_member,
+            &result,
+            &modal_data.name,
+            panel_role,
+            VerificationPath::Database,
+        )
+        .await;
+    }
+
+    state.throttle.record_failure(guild_id, user_id);
+
+    state
+        .audit
+        .record(
+            ctx,
+            AuditRecord::new(
+                &interaction.user,
+                student_id,
+                VerificationOutcome::NameMismatch,
+                VerificationPath::Database,
+            ),
+        )
+        .await;
+
+    let attempt = ReviewAttempt::new(
+        student_id,
+        &modal_data.name,
+        VerificationOutcome::NameMismatch,
+        Some(result_name.to_string()),
+        panel_role,
+    );
+
+    let followup = failure_followup(
+        data,
+        user_id,
+        attempt,
+        VERIFICATION_FAILED_TITLE,
+        VERIFICATION_FAILED_DESCRIPTION,
+    );
+
+    interaction.create_followup(ctx, followup).await?;
+
     Ok(())
 }
@@ -5,6 +5,6 @@ pub async fn on_ready(
     _ctx: &serenity::Context,
     data_about_bot: &serenity::Ready,
 ) -> Result<(), Error> {
-    println!("Logged in as {}", data_about_bot.user.name);
+    tracing::info!("Logged in as {}", data_about_bot.user.name);
     Ok(())
 }
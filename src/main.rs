@@ -1,13 +1,39 @@
-use dotenv::dotenv;
 use poise::serenity_prelude as serenity;
-use std::{collections::HashMap, sync::Mutex};
+use std::sync::Arc;
 use supabase::Client;
 mod commands;
+mod config;
+mod errors;
 mod events;
+mod metrics;
+mod monitoring;
+mod registration;
+mod telemetry;
+mod verification;
+
+use config::BotConfig;
+use metrics::Metrics;
+use monitoring::GatewayHealth;
+use registration::RegistrationStore;
+use verification::{
+    audit::AuditLog,
+    cache::MemberCache,
+    directory::MemberDirectory,
+    email::EmailCodes,
+    import::ImportQueue,
+    links::{EmailLinkStore, LinkStore},
+    names::NameMatcher,
+    onboarding::Onboarding,
+    review::ReviewQueue,
+    sweeper::Sweeper,
+    throttle::VerifyThrottle,
+    tiers::TierRoles,
+};
 
 #[derive(Debug)]
 pub struct Data {
-    pub state: AppState,
+    pub config: Arc<BotConfig>,
+    pub state: Arc<AppState>,
 }
 
 // Types used by all command functions
@@ -17,20 +43,50 @@ type ApplicationContext<'a> = poise::ApplicationContext<'a, Data, Error>;
 
 #[derive(Debug)]
 pub struct AppState {
+    /// same config as `Data`, for background tasks that only get the state
+    pub config: Arc<BotConfig>,
     pub supabase: Client,
-    pub student_cache: Mutex<HashMap<String, String>>,
+    pub directory: Arc<dyn MemberDirectory>,
+    pub student_cache: MemberCache,
+    pub throttle: VerifyThrottle,
+    pub audit: AuditLog,
+    pub links: LinkStore,
+    pub email_links: EmailLinkStore,
+    pub names: NameMatcher,
+    pub tiers: TierRoles,
+    pub onboarding: Onboarding,
+    pub email: EmailCodes,
+    pub reviews: ReviewQueue,
+    pub imports: ImportQueue,
+    pub sweeper: Sweeper,
+    pub registrations: RegistrationStore,
+    pub metrics: Arc<Metrics>,
+    pub gateway: GatewayHealth,
 }
 
 impl AppState {
-    pub fn new() -> supabase::Result<Self> {
-        dotenv().ok();
-        let supabase_url = std::env::var("SUPABASE_URL").expect("missing SUPABASE_URL");
-        let supabase_key = std::env::var("SUPABASE_KEY").expect("missing SUPABASE_KEY");
-        let client = Client::new(&supabase_url, &supabase_key)?;
+    pub fn new(config: Arc<BotConfig>) -> Result<Self, Error> {
+        let client = Client::new(&config.supabase_url, config.supabase_key.expose())?;
 
         Ok(Self {
+            audit: AuditLog::new(client.clone(), config.mod_log_channel),
+            links: LinkStore::new(client.clone()),
+            email_links: EmailLinkStore::new(client.clone()),
+            directory: verification::directory::open(&config.directory, client.clone())?,
+            names: NameMatcher::new(config.name_match_threshold),
+            tiers: TierRoles::new(config.tier_roles.clone()),
+            onboarding: Onboarding::new(config.onboarding.clone(), client.clone()),
+            email: EmailCodes::new(config.email.clone())?,
+            reviews: ReviewQueue::new(config.review_channel),
+            imports: ImportQueue::new(),
+            sweeper: Sweeper::new(config.sweep.clone(), client.clone()),
+            registrations: RegistrationStore::new(client.clone()),
+            metrics: Arc::new(Metrics::new()),
+            gateway: GatewayHealth::new(),
             supabase: client,
-            student_cache: Mutex::new(HashMap::new()),
+            student_cache: MemberCache::new(config.cache.clone()),
+            throttle: VerifyThrottle::new(config.throttle.clone()),
+            config,
         })
     }
 }
@@ -46,7 +102,10 @@ async fn event_handler(
             events::ready::on_ready(ctx, data_about_bot).await?;
         }
         serenity::FullEvent::InteractionCreate { interaction } => {
-            events::interaction_create::on_interaction_create(ctx, interaction, &data).await?;
+            events::interaction_create::on_interaction_create(ctx, interaction, data).await?;
+        }
+        serenity::FullEvent::GuildMemberAddition { new_member } => {
+            events::guild_member_addition::on_guild_member_addition(ctx, new_member, data).await?;
         }
         _ => {}
     }
@@ -55,13 +114,36 @@ async fn event_handler(
 
 #[tokio::main]
 async fn main() {
-    dotenv().ok(); // load env
+    // every missing or invalid setting is reported here, before connecting to anything
+    let config = match BotConfig::load() {
+        Ok(config) => Arc::new(config),
+        Err(err) => {
+            // logging isn't set up yet, it's configured here too
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    telemetry::init(&config.log_filter, config.log_format);
+
+    let app_state = Arc::new(AppState::new(config.clone()).expect("Failed to initialize AppState"));
+
+    // lets a signup platform or script update the member list without waiting for an import
+    tokio::spawn(verification::push::serve(
+        app_state.clone(),
+        config.push.clone(),
+    ));
 
-    let app_state = AppState::new().expect("Failed to initialize AppState");
+    // the framework setup takes ownership of the state, keep what the monitoring endpoint needs
+    let monitoring_state = app_state.clone();
+    let monitoring_addr = config.monitoring_addr;
+    let metrics = app_state.metrics.clone();
 
     // -- discord bot start --
-    let token = std::env::var("DISCORD_TOKEN").expect("missing DISCORD_TOKEN");
-    let intents = serenity::GatewayIntents::non_privileged();
+    let token = config.discord_token.expose().to_string();
+    // member joins are a privileged intent, enable "Server Members" for the bot
+    let intents =
+        serenity::GatewayIntents::non_privileged() | serenity::GatewayIntents::GUILD_MEMBERS;
 
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
@@ -73,30 +155,74 @@ async fn main() {
                 commands::info::botinfo(),
                 commands::weather::weather(),
                 commands::verification::verify(),
+                commands::verification::unverify(),
+                commands::verification::verify_status(),
+                commands::verification::force_verify(),
+                commands::verification::link(),
+                commands::verification::sweep(),
+                commands::verification::reconcile(),
+                commands::verification::cache(),
+                commands::verification::import_members(),
+                commands::verification::membership(),
+                commands::verification::privacy(),
                 commands::mods_only::embed(),
+                commands::mods_only::unregister_commands(),
             ],
             event_handler: |ctx, event, framework, data| {
                 Box::pin(event_handler(ctx, event, framework, data))
             },
+            on_error: |error| Box::pin(errors::on_error(error)),
 
             ..Default::default()
         })
-        .setup(|ctx, _ready, framework| {
+        .setup(|ctx, ready, framework| {
             Box::pin(async move {
-                poise::builtins::register_globally(ctx, &framework.options().commands).await?;
-                Ok(Data { state: app_state })
+                let scope = config.command_scope;
+                let registered = registration::register(
+                    ctx,
+                    &app_state.registrations,
+                    ready.application.id,
+                    scope,
+                    &framework.options().commands,
+                )
+                .await?;
+
+                if registered {
+                    tracing::info!("Registered commands {}", scope);
+                } else {
+                    tracing::info!("Commands unchanged since the last registration {}", scope);
+                }
+
+                if app_state.sweeper.config().enabled {
+                    tokio::spawn(verification::sweeper::run_periodically(
+                        ctx.clone(),
+                        app_state.clone(),
+                    ));
+                }
+
+                Ok(Data {
+                    config,
+                    state: app_state,
+                })
             })
         })
         .build();
 
     let client = serenity::ClientBuilder::new(token, intents)
-        .framework(framework)
+        .framework(telemetry::TracedFramework {
+            inner: framework,
+            metrics: metrics.clone(),
+        })
         .await;
-    client
-        .expect("Client failed to start")
-        .start()
-        .await
-        .expect("Client failed to start 2");
+    let mut client = client.expect("Client failed to start");
+
+    tokio::spawn(monitoring::watch_gateway(
+        client.shard_manager.clone(),
+        monitoring_state.clone(),
+    ));
+    tokio::spawn(monitoring::serve(monitoring_state, monitoring_addr));
+
+    client.start().await.expect("Client failed to start 2");
 
     // -- discord bot end --
 }
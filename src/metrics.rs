@@ -0,0 +1,279 @@
+use std::{collections::BTreeMap, fmt::Write, sync::Mutex, time::Duration};
+
+use crate::verification::{audit::VerificationOutcome, cache::CacheStats};
+
+// upper bounds of the command latency histogram, in seconds
+const LATENCY_BUCKETS: [f64; 9] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    // one count per bucket, not cumulative
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if let Some(index) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[index] += 1;
+        }
+
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+/// Counters the bot keeps for `/metrics`, everything resets on restart
+#[derive(Debug, Default)]
+pub struct Metrics {
+    commands: Mutex<BTreeMap<String, Histogram>>,
+    upstream_errors: Mutex<BTreeMap<&'static str, u64>>,
+}
+
+/// Gateway state shown next to the counters
+#[derive(Debug, Clone, Copy)]
+pub struct GatewayStats {
+    pub connected: bool,
+    pub latency: Option<Duration>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_command(&self, command: &str, latency: Duration) {
+        self.commands
+            .lock()
+            .expect("Failed to get command metrics")
+            .entry(command.to_string())
+            .or_default()
+            .observe(latency.as_secs_f64());
+    }
+
+    /// `service` is one of the names from `errors::upstream_service`
+    pub fn record_upstream_error(&self, service: &'static str) {
+        *self
+            .upstream_errors
+            .lock()
+            .expect("Failed to get upstream error metrics")
+            .entry(service)
+            .or_default() += 1;
+    }
+
+    /// Prometheus text format, with the counters kept elsewhere passed in
+    pub fn render(
+        &self,
+        outcomes: &[(VerificationOutcome, u64)],
+        cache: CacheStats,
+        gateway: GatewayStats,
+    ) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "dsec_commands_total",
+            "counter",
+            "Slash commands handled",
+        );
+        let commands = self
+            .commands
+            .lock()
+            .expect("Failed to get command metrics")
+            .clone();
+        for (command, histogram) in &commands {
+            let _ = writeln!(
+                out,
+                "dsec_commands_total{{command=\"{}\"}} {}",
+                escape(command),
+                histogram.count
+            );
+        }
+
+        header(
+            &mut out,
+            "dsec_command_duration_seconds",
+            "histogram",
+            "Time taken to handle slash commands",
+        );
+        for (command, histogram) in &commands {
+            let command = escape(command);
+            let mut cumulative = 0;
+
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "dsec_command_duration_seconds_bucket{{command=\"{}\",le=\"{}\"}} {}",
+                    command, bound, cumulative
+                );
+            }
+
+            let _ = writeln!(
+                out,
+                "dsec_command_duration_seconds_bucket{{command=\"{}\",le=\"+Inf\"}} {}",
+                command, histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "dsec_command_duration_seconds_sum{{command=\"{}\"}} {}",
+                command, histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "dsec_command_duration_seconds_count{{command=\"{}\"}} {}",
+                command, histogram.count
+            );
+        }
+
+        header(
+            &mut out,
+            "dsec_verification_outcomes_total",
+            "counter",
+            "Verification attempts by outcome",
+        );
+        for (outcome, count) in outcomes {
+            let _ = writeln!(
+                out,
+                "dsec_verification_outcomes_total{{outcome=\"{}\"}} {}",
+                outcome.key(),
+                count
+            );
+        }
+
+        header(
+            &mut out,
+            "dsec_member_cache_hits_total",
+            "counter",
+            "Membership lookups answered by the cache",
+        );
+        let _ = writeln!(out, "dsec_member_cache_hits_total {}", cache.hits);
+        header(
+            &mut out,
+            "dsec_member_cache_misses_total",
+            "counter",
+            "Membership lookups that went to the member directory",
+        );
+        let _ = writeln!(out, "dsec_member_cache_misses_total {}", cache.misses);
+        header(
+            &mut out,
+            "dsec_member_cache_hit_ratio",
+            "gauge",
+            "Share of membership lookups answered by the cache",
+        );
+        let _ = writeln!(out, "dsec_member_cache_hit_ratio {}", cache.hit_rate());
+        header(
+            &mut out,
+            "dsec_member_cache_entries",
+            "gauge",
+            "Entries in the membership cache",
+        );
+        let _ = writeln!(out, "dsec_member_cache_entries {}", cache.entries);
+
+        header(
+            &mut out,
+            "dsec_upstream_errors_total",
+            "counter",
+            "Failed calls to Discord, Supabase, the weather API or the mail server",
+        );
+        for (service, count) in self
+            .upstream_errors
+            .lock()
+            .expect("Failed to get upstream error metrics")
+            .iter()
+        {
+            let _ = writeln!(
+                out,
+                "dsec_upstream_errors_total{{service=\"{}\"}} {}",
+                service, count
+            );
+        }
+
+        header(
+            &mut out,
+            "dsec_gateway_connected",
+            "gauge",
+            "Whether every shard is connected to the Discord gateway",
+        );
+        let _ = writeln!(out, "dsec_gateway_connected {}", gateway.connected as u8);
+        if let Some(latency) = gateway.latency {
+            header(
+                &mut out,
+                "dsec_gateway_latency_seconds",
+                "gauge",
+                "Time between the last heartbeat and its acknowledgement",
+            );
+            let _ = writeln!(
+                out,
+                "dsec_gateway_latency_seconds {}",
+                latency.as_secs_f64()
+            );
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_cumulative_buckets() {
+        let metrics = Metrics::new();
+        metrics.record_command("verify", Duration::from_millis(80));
+        metrics.record_command("verify", Duration::from_millis(400));
+        metrics.record_command("verify", Duration::from_secs(60));
+        metrics.record_upstream_error("supabase");
+
+        let text = metrics.render(
+            &[(VerificationOutcome::Verified, 2)],
+            CacheStats {
+                entries: 1,
+                hits: 3,
+                misses: 1,
+            },
+            GatewayStats {
+                connected: true,
+                latency: None,
+            },
+        );
+
+        assert!(text.contains("dsec_commands_total{command=\"verify\"} 3\n"));
+        assert!(
+            text.contains(
+                "dsec_command_duration_seconds_bucket{command=\"verify\",le=\"0.05\"} 0\n"
+            )
+        );
+        assert!(
+            text.contains(
+                "dsec_command_duration_seconds_bucket{command=\"verify\",le=\"0.1\"} 1\n"
+            )
+        );
+        assert!(
+            text.contains("dsec_command_duration_seconds_bucket{command=\"verify\",le=\"30\"} 2\n")
+        );
+        assert!(
+            text.contains(
+                "dsec_command_duration_seconds_bucket{command=\"verify\",le=\"+Inf\"} 3\n"
+            )
+        );
+        assert!(text.contains("dsec_verification_outcomes_total{outcome=\"verified\"} 2\n"));
+        assert!(text.contains("dsec_member_cache_hit_ratio 0.75\n"));
+        assert!(text.contains("dsec_upstream_errors_total{service=\"supabase\"} 1\n"));
+        assert!(text.contains("dsec_gateway_connected 1\n"));
+    }
+}
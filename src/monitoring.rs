@@ -0,0 +1,230 @@
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use poise::serenity_prelude::{ConnectionStage, ShardManager};
+use serde_json::json;
+
+use crate::{AppState, metrics::GatewayStats};
+
+// how often the shard runners are checked for a new heartbeat
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+// Discord asks for a heartbeat about every 41 seconds, so this allows for one missed
+const HEARTBEAT_MAX_AGE: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy)]
+struct ShardHealth {
+    stage: ConnectionStage,
+    latency: Option<Duration>,
+    last_heartbeat: Option<Instant>,
+}
+
+/// Connection stage and last heartbeat of each shard, polled from the shard manager
+#[derive(Debug, Default)]
+pub struct GatewayHealth {
+    shards: Mutex<BTreeMap<u32, ShardHealth>>,
+}
+
+impl GatewayHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // serenity only exposes the latency of the last heartbeat, so a new value means a new heartbeat
+    fn update(
+        &self,
+        shard_id: u32,
+        stage: ConnectionStage,
+        latency: Option<Duration>,
+        now: Instant,
+    ) {
+        let mut shards = self.shards.lock().expect("Failed to get shard health");
+        let shard = shards.entry(shard_id).or_insert(ShardHealth {
+            stage,
+            latency: None,
+            last_heartbeat: None,
+        });
+
+        if latency.is_some() && latency != shard.latency {
+            shard.last_heartbeat = Some(now);
+        }
+
+        shard.stage = stage;
+        shard.latency = latency;
+    }
+
+    /// Every shard is connected and heartbeating
+    fn is_healthy(&self, now: Instant) -> bool {
+        let shards = self.shards.lock().expect("Failed to get shard health");
+
+        !shards.is_empty()
+            && shards.values().all(|shard| {
+                shard.stage == ConnectionStage::Connected
+                    && shard
+                        .last_heartbeat
+                        .is_some_and(|at| now.duration_since(at) <= HEARTBEAT_MAX_AGE)
+            })
+    }
+
+    pub fn stats(&self) -> GatewayStats {
+        let shards = self.shards.lock().expect("Failed to get shard health");
+
+        GatewayStats {
+            connected: !shards.is_empty()
+                && shards
+                    .values()
+                    .all(|shard| shard.stage == ConnectionStage::Connected),
+            latency: shards.values().filter_map(|shard| shard.latency).max(),
+        }
+    }
+
+    fn to_json(&self, now: Instant) -> serde_json::Value {
+        let shards: Vec<serde_json::Value> = self
+            .shards
+            .lock()
+            .expect("Failed to get shard health")
+            .iter()
+            .map(|(shard_id, shard)| {
+                json!({
+                    "id": shard_id,
+                    "stage": shard.stage.to_string(),
+                    "latency_ms": shard.latency.map(|latency| latency.as_millis() as u64),
+                    "last_heartbeat_secs_ago": shard
+                        .last_heartbeat
+                        .map(|at| now.duration_since(at).as_secs()),
+                })
+            })
+            .collect();
+
+        json!({
+            "status": if self.is_healthy(now) { "ok" } else { "unavailable" },
+            "shards": shards,
+        })
+    }
+}
+
+/// Copies the shard runners' state into `state.gateway` until the process exits
+pub async fn watch_gateway(shard_manager: Arc<ShardManager>, state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let runners = shard_manager.runners.lock().await;
+        for (shard_id, runner) in runners.iter() {
+            state
+                .gateway
+                .update(shard_id.0, runner.stage, runner.latency, Instant::now());
+        }
+    }
+}
+
+async fn healthz(State(state): State<Arc<AppState>>) -> Response {
+    let now = Instant::now();
+    let status = if state.gateway.is_healthy(now) {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(state.gateway.to_json(now))).into_response()
+}
+
+async fn metrics(State(state): State<Arc<AppState>>) -> Response {
+    let body = state.metrics.render(
+        &state.audit.outcome_counts(),
+        state.student_cache.stats(),
+        state.gateway.stats(),
+    );
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
+
+pub fn router(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/metrics", get(metrics))
+        .with_state(state)
+}
+
+/// Serves `/healthz` and `/metrics` until the process exits, doing nothing when no address is configured
+pub async fn serve(state: Arc<AppState>, addr: Option<SocketAddr>) {
+    let Some(addr) = addr else {
+        return;
+    };
+
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            tracing::error!(%addr, error = %err, "Failed to bind monitoring endpoint");
+            return;
+        }
+    };
+
+    tracing::info!(%addr, "Monitoring endpoint listening");
+
+    if let Err(err) = axum::serve(listener, router(state)).await {
+        tracing::error!(error = %err, "Monitoring endpoint stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn needs_a_recent_heartbeat() {
+        let health = GatewayHealth::new();
+        let start = Instant::now();
+        assert!(!health.is_healthy(start));
+
+        // connected, but no heartbeat acknowledged yet
+        health.update(0, ConnectionStage::Connected, None, start);
+        assert!(!health.is_healthy(start));
+
+        health.update(
+            0,
+            ConnectionStage::Connected,
+            Some(Duration::from_millis(40)),
+            start,
+        );
+        assert!(health.is_healthy(start));
+
+        // same latency as before, so no new heartbeat
+        let later = start + Duration::from_secs(130);
+        health.update(
+            0,
+            ConnectionStage::Connected,
+            Some(Duration::from_millis(40)),
+            later,
+        );
+        assert!(!health.is_healthy(later));
+
+        health.update(
+            0,
+            ConnectionStage::Connected,
+            Some(Duration::from_millis(45)),
+            later,
+        );
+        assert!(health.is_healthy(later));
+
+        health.update(
+            0,
+            ConnectionStage::Resuming,
+            Some(Duration::from_millis(45)),
+            later,
+        );
+        assert!(!health.is_healthy(later));
+        assert!(!health.stats().connected);
+    }
+}
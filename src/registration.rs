@@ -0,0 +1,209 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use poise::serenity_prelude::{self as serenity, ApplicationId, Command, GuildId, Timestamp};
+use serde::{Deserialize, Serialize};
+use supabase::Client;
+use tracing::instrument;
+
+use crate::{Data, Error};
+
+const REGISTRATIONS_TABLE: &str = "command_registrations";
+
+/// Where slash commands are registered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandScope {
+    /// every server the bot is in, can take a while to show up
+    Global,
+    /// one server only, updates straight away, for development
+    Guild(GuildId),
+}
+
+impl CommandScope {
+    // the same stored hash must not be shared by two bots using one database
+    fn key(&self, application_id: ApplicationId) -> String {
+        match self {
+            CommandScope::Global => format!("{}:global", application_id),
+            CommandScope::Guild(guild_id) => format!("{}:guild:{}", application_id, guild_id),
+        }
+    }
+
+    async fn set_commands(
+        &self,
+        ctx: &serenity::Context,
+        commands: Vec<serenity::CreateCommand>,
+    ) -> Result<(), Error> {
+        match self {
+            CommandScope::Global => {
+                Command::set_global_commands(ctx, commands).await?;
+            }
+            CommandScope::Guild(guild_id) => {
+                guild_id.set_commands(ctx, commands).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for CommandScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandScope::Global => write!(f, "globally"),
+            CommandScope::Guild(guild_id) => write!(f, "in guild {}", guild_id),
+        }
+    }
+}
+
+/// The command set last registered in a scope
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Registration {
+    scope: String,
+    hash: String,
+    registered_at: Timestamp,
+}
+
+/// Reads and writes the `command_registrations` table
+#[derive(Debug)]
+pub struct RegistrationStore {
+    supabase: Client,
+}
+
+impl RegistrationStore {
+    pub fn new(supabase: Client) -> Self {
+        Self { supabase }
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = REGISTRATIONS_TABLE, query = "find_hash"))]
+    async fn find_hash(&self, scope: &str) -> supabase::Result<Option<String>> {
+        let registrations: Vec<Registration> = self
+            .supabase
+            .database()
+            .from(REGISTRATIONS_TABLE)
+            .select("*")
+            .eq("scope", scope)
+            .execute()
+            .await?;
+
+        Ok(registrations
+            .into_iter()
+            .next()
+            .map(|registration| registration.hash))
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = REGISTRATIONS_TABLE, query = "save"))]
+    async fn save(&self, scope: String, hash: String) -> supabase::Result<()> {
+        let registration = Registration {
+            scope,
+            hash,
+            registered_at: Timestamp::now(),
+        };
+
+        self.supabase
+            .database()
+            .upsert(REGISTRATIONS_TABLE)
+            .values(&registration)?
+            .returning("*")
+            .execute::<Registration>()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = REGISTRATIONS_TABLE, query = "forget"))]
+    async fn forget(&self, scope: &str) -> supabase::Result<()> {
+        self.supabase
+            .database()
+            .delete(REGISTRATIONS_TABLE)
+            .eq("scope", scope)
+            .returning("*")
+            .execute::<Registration>()
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Fingerprint of the commands as they would be sent to Discord
+pub fn command_hash(commands: &[poise::Command<Data, Error>]) -> Result<String, Error> {
+    let json = serde_json::to_string(&poise::builtins::create_application_commands(commands))?;
+
+    // not stable across Rust releases, which only costs one extra registration after an upgrade
+    let mut hasher = DefaultHasher::new();
+    json.hash(&mut hasher);
+
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+/// Registers the commands in the scope, unless the same set was already registered there.
+/// Returns whether Discord was called.
+pub async fn register(
+    ctx: &serenity::Context,
+    store: &RegistrationStore,
+    application_id: ApplicationId,
+    scope: CommandScope,
+    commands: &[poise::Command<Data, Error>],
+) -> Result<bool, Error> {
+    let key = scope.key(application_id);
+    let hash = command_hash(commands)?;
+
+    // a failed lookup only means registering again
+    match store.find_hash(&key).await {
+        Ok(Some(stored)) if stored == hash => return Ok(false),
+        Ok(_) => {}
+        Err(err) => tracing::warn!(error = %err, "Failed to read the registered command hash"),
+    }
+
+    scope
+        .set_commands(ctx, poise::builtins::create_application_commands(commands))
+        .await?;
+
+    if let Err(err) = store.save(key, hash).await {
+        tracing::warn!(error = %err, "Failed to store the registered command hash");
+    }
+
+    Ok(true)
+}
+
+/// Removes every command the bot registered in the scope
+pub async fn unregister(
+    ctx: &serenity::Context,
+    store: &RegistrationStore,
+    application_id: ApplicationId,
+    scope: CommandScope,
+) -> Result<(), Error> {
+    scope.set_commands(ctx, Vec::new()).await?;
+
+    // so registering in this scope again isn't skipped
+    store.forget(&scope.key(application_id)).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::info::{help, ping};
+
+    #[test]
+    fn hash_changes_with_the_command_set() {
+        let one = command_hash(&[ping()]).unwrap();
+
+        assert_eq!(one, command_hash(&[ping()]).unwrap());
+        assert_ne!(one, command_hash(&[ping(), help()]).unwrap());
+    }
+
+    #[test]
+    fn scope_keys_include_the_application() {
+        let application_id = ApplicationId::new(1);
+
+        assert_eq!(CommandScope::Global.key(application_id), "1:global");
+        assert_eq!(
+            CommandScope::Guild(GuildId::new(2)).key(application_id),
+            "1:guild:2"
+        );
+    }
+}
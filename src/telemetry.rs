@@ -0,0 +1,143 @@
+use std::{fmt, str::FromStr, sync::Arc, time::Instant};
+
+use async_trait::async_trait;
+use poise::serenity_prelude::{self as serenity, CommandDataOptionValue, CommandInteraction};
+use tracing::{Instrument, Span, field, info, info_span};
+use tracing_subscriber::EnvFilter;
+
+use crate::metrics::Metrics;
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// human readable, for running locally
+    Text,
+    /// one JSON object per line, for log collectors
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err("expected \"text\" or \"json\"".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        })
+    }
+}
+
+/// Installs the global subscriber, the filter uses `RUST_LOG` syntax and was checked by the config
+pub fn init(filter: &str, format: LogFormat) {
+    let builder = tracing_subscriber::fmt().with_env_filter(EnvFilter::new(filter));
+
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().with_current_span(true).init(),
+    }
+}
+
+// e.g. "link view" for `/link view`
+fn command_name(interaction: &CommandInteraction) -> String {
+    let mut name = interaction.data.name.clone();
+    let mut options = &interaction.data.options;
+
+    while let Some(option) = options.first() {
+        match &option.value {
+            CommandDataOptionValue::SubCommand(inner)
+            | CommandDataOptionValue::SubCommandGroup(inner) => {
+                name.push(' ');
+                name.push_str(&option.name);
+                options = inner;
+            }
+            _ => break,
+        }
+    }
+
+    name
+}
+
+// the span, and the command name when it's a slash command
+fn interaction_span(event: &serenity::FullEvent) -> Option<(Span, Option<String>)> {
+    let serenity::FullEvent::InteractionCreate { interaction } = event else {
+        return None;
+    };
+
+    let span = match interaction {
+        serenity::Interaction::Command(interaction) => {
+            let command = command_name(interaction);
+            let span = info_span!(
+                "command",
+                guild = interaction.guild_id.map(|id| id.get()),
+                user = interaction.user.id.get(),
+                command = %command,
+                latency_ms = field::Empty,
+            );
+
+            return Some((span, Some(command)));
+        }
+        serenity::Interaction::Component(interaction) => info_span!(
+            "interaction",
+            guild = interaction.guild_id.map(|id| id.get()),
+            user = interaction.user.id.get(),
+            custom_id = %interaction.data.custom_id,
+            latency_ms = field::Empty,
+        ),
+        serenity::Interaction::Modal(interaction) => info_span!(
+            "interaction",
+            guild = interaction.guild_id.map(|id| id.get()),
+            user = interaction.user.id.get(),
+            custom_id = %interaction.data.custom_id,
+            latency_ms = field::Empty,
+        ),
+        _ => return None,
+    };
+
+    Some((span, None))
+}
+
+/// Runs every command and component interaction inside a span, with its latency once done.
+/// Command latencies also go to the metrics.
+pub struct TracedFramework<F> {
+    pub inner: F,
+    pub metrics: Arc<Metrics>,
+}
+
+#[async_trait]
+impl<F: serenity::Framework> serenity::Framework for TracedFramework<F> {
+    async fn init(&mut self, client: &serenity::Client) {
+        self.inner.init(client).await;
+    }
+
+    async fn dispatch(&self, ctx: serenity::Context, event: serenity::FullEvent) {
+        let Some((span, command)) = interaction_span(&event) else {
+            self.inner.dispatch(ctx, event).await;
+            return;
+        };
+
+        let started = Instant::now();
+        self.inner
+            .dispatch(ctx, event)
+            .instrument(span.clone())
+            .await;
+        let latency = started.elapsed();
+
+        if let Some(command) = command {
+            self.metrics.record_command(&command, latency);
+        }
+
+        let latency_ms = latency.as_millis() as u64;
+        span.record("latency_ms", latency_ms);
+        span.in_scope(|| info!(latency_ms, "handled"));
+    }
+}
@@ -0,0 +1,19 @@
+// this file is to let main.rs know the existence of the "verification" folder
+pub mod audit;
+pub mod cache;
+pub mod directory;
+pub mod email;
+pub mod import;
+pub mod links;
+pub mod names;
+pub mod onboarding;
+pub mod panel;
+pub mod privacy;
+pub mod push;
+pub mod reconcile;
+pub mod review;
+pub mod roles;
+pub mod student_id;
+pub mod sweeper;
+pub mod throttle;
+pub mod tiers;
@@ -0,0 +1,302 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use serenity::all::{
+    ChannelId, Colour, CreateEmbed, CreateEmbedFooter, CreateMessage, Timestamp, User, UserId,
+};
+use supabase::{Client, types::OrderDirection};
+use tracing::instrument;
+
+const AUDIT_TABLE: &str = "verification_audit";
+
+/// Result of a single verification attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationOutcome {
+    Verified,
+    NameMismatch,
+    NotFound,
+    AlreadyLinked,
+    LinkTransferred,
+    LinkCleared,
+    ReviewRequested,
+    ReviewApproved,
+    ReviewRejected,
+    MembershipExpired,
+    Revoked,
+    ForceVerified,
+    Restored,
+    EmailVerified,
+    EmailCodeFailed,
+}
+
+impl VerificationOutcome {
+    /// Same as the serialised value, used as a metrics label
+    pub fn key(&self) -> &'static str {
+        match self {
+            VerificationOutcome::Verified => "verified",
+            VerificationOutcome::NameMismatch => "name_mismatch",
+            VerificationOutcome::NotFound => "not_found",
+            VerificationOutcome::AlreadyLinked => "already_linked",
+            VerificationOutcome::LinkTransferred => "link_transferred",
+            VerificationOutcome::LinkCleared => "link_cleared",
+            VerificationOutcome::ReviewRequested => "review_requested",
+            VerificationOutcome::ReviewApproved => "review_approved",
+            VerificationOutcome::ReviewRejected => "review_rejected",
+            VerificationOutcome::MembershipExpired => "membership_expired",
+            VerificationOutcome::Revoked => "revoked",
+            VerificationOutcome::ForceVerified => "force_verified",
+            VerificationOutcome::Restored => "restored",
+            VerificationOutcome::EmailVerified => "email_verified",
+            VerificationOutcome::EmailCodeFailed => "email_code_failed",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            VerificationOutcome::Verified => "Verified ✅",
+            VerificationOutcome::NameMismatch => "Name mismatch ❌",
+            VerificationOutcome::NotFound => "Student ID not found ❌",
+            VerificationOutcome::AlreadyLinked => "Student ID linked to another account ⚠️",
+            VerificationOutcome::LinkTransferred => "Student ID link transferred 🔁",
+            VerificationOutcome::LinkCleared => "Student ID link cleared 🧹",
+            VerificationOutcome::ReviewRequested => "Manual review requested 📝",
+            VerificationOutcome::ReviewApproved => "Manual review approved ✅",
+            VerificationOutcome::ReviewRejected => "Manual review rejected ❌",
+            VerificationOutcome::MembershipExpired => "Membership expired, role removed ⌛",
+            VerificationOutcome::Revoked => "Verification revoked 🚫",
+            VerificationOutcome::ForceVerified => "Verified by committee 🛠️",
+            VerificationOutcome::Restored => "Verified role restored on rejoin 🔄",
+            VerificationOutcome::EmailVerified => "Verified by email ✉️",
+            VerificationOutcome::EmailCodeFailed => "Email code attempts used up ❌",
+        }
+    }
+
+    pub fn colour(&self) -> Colour {
+        match self {
+            VerificationOutcome::Verified
+            | VerificationOutcome::ReviewApproved
+            | VerificationOutcome::ForceVerified
+            | VerificationOutcome::Restored
+            | VerificationOutcome::EmailVerified => Colour::DARK_GREEN,
+            VerificationOutcome::NameMismatch | VerificationOutcome::MembershipExpired => {
+                Colour::ORANGE
+            }
+            VerificationOutcome::NotFound
+            | VerificationOutcome::ReviewRejected
+            | VerificationOutcome::Revoked
+            | VerificationOutcome::EmailCodeFailed => Colour::RED,
+            VerificationOutcome::AlreadyLinked | VerificationOutcome::ReviewRequested => {
+                Colour::GOLD
+            }
+            VerificationOutcome::LinkTransferred | VerificationOutcome::LinkCleared => {
+                Colour::BLURPLE
+            }
+        }
+    }
+}
+
+/// Where the membership data for an attempt came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationPath {
+    Cache,
+    Database,
+    /// changed by a committee member rather than the verify button
+    Manual,
+    /// changed by a background task such as the membership sweeper
+    Automatic,
+    /// a one-time code sent to a university email address
+    Email,
+}
+
+impl VerificationPath {
+    pub fn label(&self) -> &'static str {
+        match self {
+            VerificationPath::Cache => "⚡ cache",
+            VerificationPath::Database => "🗄️ database",
+            VerificationPath::Manual => "🛠️ committee",
+            VerificationPath::Automatic => "🤖 automatic",
+            VerificationPath::Email => "✉️ email",
+        }
+    }
+}
+
+/// One row of the `verification_audit` table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub discord_user_id: UserId,
+    pub discord_username: String,
+    pub student_id: String,
+    pub outcome: VerificationOutcome,
+    pub path: VerificationPath,
+    pub details: Option<String>,
+    pub created_at: Timestamp,
+}
+
+impl AuditRecord {
+    pub fn new(
+        user: &User,
+        student_id: impl Into<String>,
+        outcome: VerificationOutcome,
+        path: VerificationPath,
+    ) -> Self {
+        Self {
+            discord_user_id: user.id,
+            discord_username: user.name.clone(),
+            student_id: student_id.into(),
+            outcome,
+            path,
+            details: None,
+            created_at: Timestamp::now(),
+        }
+    }
+
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+
+    fn to_embed(&self) -> CreateEmbed {
+        let student_id = if self.student_id.is_empty() {
+            "None".to_string()
+        } else {
+            format!("s{}", self.student_id)
+        };
+
+        let mut embed = CreateEmbed::new()
+            .title(self.outcome.title())
+            .field("User", format!("<@{}>", self.discord_user_id), true)
+            .field("Student ID", student_id, true)
+            .field("Path", self.path.label(), true)
+            .colour(self.outcome.colour())
+            .footer(CreateEmbedFooter::new(format!(
+                "{} • ID: {}",
+                self.discord_username, self.discord_user_id
+            )))
+            .timestamp(self.created_at);
+
+        if let Some(details) = &self.details {
+            embed = embed.field("Details", details, false);
+        }
+
+        embed
+    }
+}
+
+/// Records verification attempts in Supabase and the committee's mod-log channel
+#[derive(Debug)]
+pub struct AuditLog {
+    supabase: Client,
+    mod_log_channel: Option<ChannelId>,
+    // attempts recorded since startup, for metrics
+    outcomes: Mutex<BTreeMap<VerificationOutcome, u64>>,
+}
+
+impl AuditLog {
+    pub fn new(supabase: Client, mod_log_channel: Option<ChannelId>) -> Self {
+        Self {
+            supabase,
+            mod_log_channel,
+            outcomes: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = AUDIT_TABLE, query = "save"))]
+    async fn save(&self, record: &AuditRecord) -> supabase::Result<()> {
+        self.supabase
+            .database()
+            .insert(AUDIT_TABLE)
+            .values(record)?
+            .returning("*")
+            .execute::<AuditRecord>()
+            .await?;
+
+        Ok(())
+    }
+
+    /// The user's most recent records, newest first
+    #[instrument(name = "supabase", skip_all, fields(table = AUDIT_TABLE, query = "history"))]
+    pub async fn history(&self, user_id: UserId, limit: u32) -> supabase::Result<Vec<AuditRecord>> {
+        self.supabase
+            .database()
+            .from(AUDIT_TABLE)
+            .select("*")
+            .eq("discord_user_id", &user_id.to_string())
+            .order("created_at", OrderDirection::Descending)
+            .limit(limit)
+            .execute()
+            .await
+    }
+
+    /// Every record for the user, newest first
+    #[instrument(name = "supabase", skip_all, fields(table = AUDIT_TABLE, query = "all_for_user"))]
+    pub async fn all_for_user(&self, user_id: UserId) -> supabase::Result<Vec<AuditRecord>> {
+        self.supabase
+            .database()
+            .from(AUDIT_TABLE)
+            .select("*")
+            .eq("discord_user_id", &user_id.to_string())
+            .order("created_at", OrderDirection::Descending)
+            .execute()
+            .await
+    }
+
+    /// Deletes every record for the user, returning how many there were
+    #[instrument(name = "supabase", skip_all, fields(table = AUDIT_TABLE, query = "delete_for_user"))]
+    pub async fn delete_for_user(&self, user_id: UserId) -> supabase::Result<usize> {
+        let removed: Vec<AuditRecord> = self
+            .supabase
+            .database()
+            .delete(AUDIT_TABLE)
+            .eq("discord_user_id", &user_id.to_string())
+            .returning("*")
+            .execute()
+            .await?;
+
+        Ok(removed.len())
+    }
+
+    /// Posts an embed to the mod-log channel, if one is configured
+    pub async fn notify(&self, ctx: &serenity::Context, embed: CreateEmbed) {
+        let Some(channel_id) = self.mod_log_channel else {
+            return;
+        };
+
+        if let Err(err) = channel_id
+            .send_message(ctx, CreateMessage::new().embed(embed))
+            .await
+        {
+            tracing::warn!(error = %err, "Failed to send to mod-log channel");
+        }
+    }
+
+    /// Saves the record and posts it to the mod-log channel.
+    ///
+    /// Failures are logged rather than returned, an audit problem should never stop someone verifying.
+    pub async fn record(&self, ctx: &serenity::Context, record: AuditRecord) {
+        *self
+            .outcomes
+            .lock()
+            .expect("Failed to get audit outcomes")
+            .entry(record.outcome)
+            .or_default() += 1;
+
+        if let Err(err) = self.save(&record).await {
+            tracing::error!(error = %err, "Failed to save verification audit record");
+        }
+
+        self.notify(ctx, record.to_embed()).await;
+    }
+
+    /// Attempts recorded since startup, by outcome
+    pub fn outcome_counts(&self) -> Vec<(VerificationOutcome, u64)> {
+        self.outcomes
+            .lock()
+            .expect("Failed to get audit outcomes")
+            .iter()
+            .map(|(outcome, count)| (*outcome, *count))
+            .collect()
+    }
+}
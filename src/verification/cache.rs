@@ -0,0 +1,310 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::commands::verification::StudentRow;
+
+/// Limits for the membership cache
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// how long a found member stays cached
+    pub ttl: Duration,
+    /// how long a student ID that wasn't found stays cached
+    pub negative_ttl: Duration,
+    /// entries kept before the least recently used one is evicted
+    pub max_entries: usize,
+}
+
+/// Result of looking up a student ID in the cache
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheLookup {
+    /// the student ID is a member, with their row in `active_members`
+    Found(StudentRow),
+    /// the student ID was recently looked up and isn't a member
+    NotFound,
+    /// nothing cached, ask the database
+    Miss,
+}
+
+#[derive(Debug)]
+struct Entry {
+    // None for a student ID that wasn't found
+    member: Option<StudentRow>,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    entries: HashMap<String, Entry>,
+    // increases on every access, the entry with the smallest `last_used` is evicted first
+    clock: u64,
+}
+
+/// Snapshot of the cache counters
+#[derive(Debug, Clone, Copy)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// Bounded, expiring cache of `active_members` lookups, keyed by normalised student ID
+#[derive(Debug)]
+pub struct MemberCache {
+    config: CacheConfig,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl MemberCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(Entries::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self, student_id: &str) -> CacheLookup {
+        self.get_at(student_id, Instant::now())
+    }
+
+    fn get_at(&self, student_id: &str, now: Instant) -> CacheLookup {
+        let mut cache = self.entries.lock().expect("Failed to get cache");
+        cache.clock += 1;
+        let clock = cache.clock;
+
+        let lookup = match cache.entries.get_mut(student_id) {
+            Some(entry) if entry.expires_at > now => {
+                entry.last_used = clock;
+
+                match &entry.member {
+                    Some(member) => CacheLookup::Found(member.clone()),
+                    None => CacheLookup::NotFound,
+                }
+            }
+            Some(_) => {
+                cache.entries.remove(student_id);
+                CacheLookup::Miss
+            }
+            None => CacheLookup::Miss,
+        };
+
+        let counter = match lookup {
+            CacheLookup::Miss => &self.misses,
+            _ => &self.hits,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        lookup
+    }
+
+    fn put(&self, student_id: &str, member: Option<StudentRow>, ttl: Duration, now: Instant) {
+        if self.config.max_entries == 0 {
+            return;
+        }
+
+        let mut cache = self.entries.lock().expect("Failed to get cache");
+        cache.clock += 1;
+        let clock = cache.clock;
+
+        if !cache.entries.contains_key(student_id) && cache.entries.len() >= self.config.max_entries
+        {
+            cache.entries.retain(|_, entry| entry.expires_at > now);
+        }
+
+        while !cache.entries.contains_key(student_id)
+            && cache.entries.len() >= self.config.max_entries
+        {
+            let least_recent = cache
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+
+            match least_recent {
+                Some(key) => cache.entries.remove(&key),
+                None => break,
+            };
+        }
+
+        cache.entries.insert(
+            student_id.to_string(),
+            Entry {
+                member,
+                expires_at: now + ttl,
+                last_used: clock,
+            },
+        );
+    }
+
+    /// Caches a member's row, keyed by their student ID
+    pub fn insert(&self, member: StudentRow) {
+        let student_id = member.student_id.clone();
+        self.put(&student_id, Some(member), self.config.ttl, Instant::now());
+    }
+
+    /// Caches that a student ID isn't in `active_members`
+    pub fn insert_not_found(&self, student_id: &str) {
+        self.put(student_id, None, self.config.negative_ttl, Instant::now());
+    }
+
+    /// Drops a student ID from the cache, returning whether it was cached
+    pub fn invalidate(&self, student_id: &str) -> bool {
+        let mut cache = self.entries.lock().expect("Failed to get cache");
+        cache.entries.remove(student_id).is_some()
+    }
+
+    /// Drops every entry, returning how many there were
+    pub fn clear(&self) -> usize {
+        let mut cache = self.entries.lock().expect("Failed to get cache");
+        let cleared = cache.entries.len();
+        cache.entries.clear();
+        cleared
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let cache = self.entries.lock().expect("Failed to get cache");
+
+        CacheStats {
+            entries: cache.entries.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(max_entries: usize) -> MemberCache {
+        MemberCache::new(CacheConfig {
+            ttl: Duration::from_secs(60),
+            negative_ttl: Duration::from_secs(10),
+            max_entries,
+        })
+    }
+
+    fn member(student_id: &str) -> StudentRow {
+        StudentRow {
+            full_name: "John Smith".to_string(),
+            student_id: student_id.to_string(),
+            membership_type: None,
+            expires_at: None,
+        }
+    }
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn entries_expire_after_their_ttl() {
+        let cache = cache(10);
+        let now = Instant::now();
+
+        cache.put("1", Some(member("1")), cache.config.ttl, now);
+        cache.put("2", None, cache.config.negative_ttl, now);
+
+        assert_eq!(
+            cache.get_at("1", now + secs(59)),
+            CacheLookup::Found(member("1"))
+        );
+        assert_eq!(cache.get_at("2", now + secs(9)), CacheLookup::NotFound);
+
+        // not found entries go first
+        assert_eq!(cache.get_at("2", now + secs(10)), CacheLookup::Miss);
+        assert_eq!(
+            cache.get_at("1", now + secs(10)),
+            CacheLookup::Found(member("1"))
+        );
+        assert_eq!(cache.get_at("1", now + secs(60)), CacheLookup::Miss);
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_entry() {
+        let cache = cache(2);
+        let now = Instant::now();
+
+        cache.put("1", Some(member("1")), cache.config.ttl, now);
+        cache.put("2", Some(member("2")), cache.config.ttl, now);
+        // "1" is now more recent than "2"
+        cache.get_at("1", now);
+        cache.put("3", Some(member("3")), cache.config.ttl, now);
+
+        assert_eq!(cache.get_at("2", now), CacheLookup::Miss);
+        assert_eq!(cache.get_at("1", now), CacheLookup::Found(member("1")));
+        assert_eq!(cache.get_at("3", now), CacheLookup::Found(member("3")));
+        assert_eq!(cache.stats().entries, 2);
+    }
+
+    #[test]
+    fn expired_entries_are_evicted_before_live_ones() {
+        let cache = cache(2);
+        let now = Instant::now();
+
+        cache.put("1", None, cache.config.negative_ttl, now);
+        cache.put("2", Some(member("2")), cache.config.ttl, now);
+        cache.get_at("1", now);
+        cache.put("3", Some(member("3")), cache.config.ttl, now + secs(20));
+
+        assert_eq!(
+            cache.get_at("2", now + secs(20)),
+            CacheLookup::Found(member("2"))
+        );
+        assert_eq!(
+            cache.get_at("3", now + secs(20)),
+            CacheLookup::Found(member("3"))
+        );
+    }
+
+    #[test]
+    fn counts_hits_and_misses() {
+        let cache = cache(10);
+
+        assert_eq!(cache.get("1"), CacheLookup::Miss);
+        cache.insert(member("1"));
+        cache.insert_not_found("2");
+        assert_eq!(cache.get("1"), CacheLookup::Found(member("1")));
+        assert_eq!(cache.get("2"), CacheLookup::NotFound);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (2, 1, 2));
+        assert!((stats.hit_rate() - 2.0 / 3.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn invalidate_drops_one_entry() {
+        let cache = cache(10);
+        cache.insert(member("1"));
+        cache.insert(member("2"));
+
+        assert!(cache.invalidate("1"));
+        assert!(!cache.invalidate("1"));
+        assert_eq!(cache.get("1"), CacheLookup::Miss);
+        assert_eq!(cache.get("2"), CacheLookup::Found(member("2")));
+        assert_eq!(cache.clear(), 1);
+    }
+}
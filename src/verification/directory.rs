@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, commands::verification::StudentRow, config::DirectoryBackend};
+
+pub mod sqlite;
+pub mod supabase;
+
+pub use self::{sqlite::SqliteDirectory, supabase::SupabaseDirectory};
+
+/// One refresh of the member list, e.g. a CSV import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncRecord {
+    /// what changed the list, e.g. "import"
+    pub source: String,
+    pub added: i64,
+    pub updated: i64,
+    pub removed: i64,
+    pub synced_at: DateTime<Utc>,
+}
+
+/// Where membership lookups go, so verification doesn't depend on a particular database
+#[async_trait]
+pub trait MemberDirectory: Send + Sync + std::fmt::Debug {
+    /// Looks up a member by normalised student ID (no leading "s")
+    async fn find_member(&self, student_id: &str) -> Result<Option<StudentRow>, Error>;
+
+    /// Looks up several members at once, student IDs that aren't members are left out
+    async fn find_members(&self, student_ids: &[String]) -> Result<Vec<StudentRow>, Error>;
+
+    /// Every member, ordered by student ID
+    async fn list_members(&self) -> Result<Vec<StudentRow>, Error>;
+
+    /// Adds the members, replacing the name on record for student IDs that already exist
+    async fn upsert_members(&self, members: &[StudentRow]) -> Result<(), Error>;
+
+    /// Removes members by student ID, IDs that aren't members are ignored
+    async fn remove_members(&self, student_ids: &[String]) -> Result<(), Error>;
+
+    /// Notes that the member list was refreshed
+    async fn record_sync(&self, sync: &SyncRecord) -> Result<(), Error>;
+
+    /// The most recent refresh of the member list, if any were recorded
+    async fn last_sync(&self) -> Result<Option<SyncRecord>, Error>;
+}
+
+/// Opens the configured backend
+pub fn open(
+    backend: &DirectoryBackend,
+    supabase: ::supabase::Client,
+) -> Result<Arc<dyn MemberDirectory>, Error> {
+    match backend {
+        DirectoryBackend::Supabase => Ok(Arc::new(SupabaseDirectory::new(supabase))),
+        DirectoryBackend::Sqlite { path, seed_path } => {
+            Ok(Arc::new(SqliteDirectory::open(path, seed_path.as_deref())?))
+        }
+    }
+}
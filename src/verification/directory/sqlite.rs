@@ -0,0 +1,308 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use rusqlite::{Connection, OptionalExtension, params, params_from_iter};
+
+use crate::{
+    Error,
+    commands::verification::StudentRow,
+    verification::directory::{MemberDirectory, SyncRecord},
+};
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS active_members (
+    student_id TEXT PRIMARY KEY,
+    full_name TEXT NOT NULL,
+    membership_type TEXT,
+    expires_at TEXT
+);
+
+CREATE TABLE IF NOT EXISTS membership_syncs (
+    source TEXT NOT NULL,
+    added INTEGER NOT NULL,
+    updated INTEGER NOT NULL,
+    removed INTEGER NOT NULL,
+    synced_at TEXT NOT NULL
+);";
+
+// columns added after the table was first created, for databases made by older versions
+const ADDED_COLUMNS: [(&str, &str); 2] = [("membership_type", "TEXT"), ("expires_at", "TEXT")];
+
+const MEMBER_COLUMNS: &str = "full_name, student_id, membership_type, expires_at";
+
+/// A local SQLite copy of `active_members`, for developing without the club's Supabase credentials
+#[derive(Debug)]
+pub struct SqliteDirectory {
+    // queries against a local file are quick enough to run without leaving the async task
+    connection: Mutex<Connection>,
+}
+
+impl SqliteDirectory {
+    /// Opens (or creates) the database file, then runs the optional seed script
+    pub fn open(path: &str, seed_path: Option<&str>) -> Result<Self, Error> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+
+        for (column, column_type) in ADDED_COLUMNS {
+            let exists: bool = connection.query_row(
+                "SELECT EXISTS (SELECT 1 FROM pragma_table_info('active_members') WHERE name = ?1)",
+                params![column],
+                |row| row.get(0),
+            )?;
+
+            if !exists {
+                connection.execute_batch(&format!(
+                    "ALTER TABLE active_members ADD COLUMN {} {}",
+                    column, column_type
+                ))?;
+            }
+        }
+
+        if let Some(seed_path) = seed_path {
+            let seed = std::fs::read_to_string(seed_path)
+                .map_err(|err| format!("Unable to read {}: {}", seed_path, err))?;
+            connection.execute_batch(&seed)?;
+        }
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+}
+
+fn student_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<StudentRow> {
+    Ok(StudentRow {
+        full_name: row.get("full_name")?,
+        student_id: row.get("student_id")?,
+        membership_type: row.get("membership_type")?,
+        expires_at: row.get("expires_at")?,
+    })
+}
+
+#[async_trait]
+impl MemberDirectory for SqliteDirectory {
+    async fn find_member(&self, student_id: &str) -> Result<Option<StudentRow>, Error> {
+        let connection = self.connection.lock().expect("Failed to get database");
+
+        let member = connection
+            .query_row(
+                &format!(
+                    "SELECT {} FROM active_members WHERE student_id = ?1",
+                    MEMBER_COLUMNS
+                ),
+                params![student_id],
+                student_row,
+            )
+            .optional()?;
+
+        Ok(member)
+    }
+
+    async fn find_members(&self, student_ids: &[String]) -> Result<Vec<StudentRow>, Error> {
+        if student_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let connection = self.connection.lock().expect("Failed to get database");
+
+        let placeholders = vec!["?"; student_ids.len()].join(", ");
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM active_members WHERE student_id IN ({})",
+            MEMBER_COLUMNS, placeholders
+        ))?;
+
+        let members = statement
+            .query_map(params_from_iter(student_ids), student_row)?
+            .collect::<rusqlite::Result<Vec<StudentRow>>>()?;
+
+        Ok(members)
+    }
+
+    async fn list_members(&self) -> Result<Vec<StudentRow>, Error> {
+        let connection = self.connection.lock().expect("Failed to get database");
+
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM active_members ORDER BY student_id",
+            MEMBER_COLUMNS
+        ))?;
+
+        let members = statement
+            .query_map([], student_row)?
+            .collect::<rusqlite::Result<Vec<StudentRow>>>()?;
+
+        Ok(members)
+    }
+
+    async fn upsert_members(&self, members: &[StudentRow]) -> Result<(), Error> {
+        let mut connection = self.connection.lock().expect("Failed to get database");
+        let transaction = connection.transaction()?;
+
+        {
+            let mut statement = transaction.prepare(
+                "INSERT OR REPLACE INTO active_members (student_id, full_name, membership_type, expires_at)
+                VALUES (?1, ?2, ?3, ?4)",
+            )?;
+
+            for member in members {
+                statement.execute(params![
+                    member.student_id,
+                    member.full_name,
+                    member.membership_type,
+                    member.expires_at
+                ])?;
+            }
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    async fn remove_members(&self, student_ids: &[String]) -> Result<(), Error> {
+        let mut connection = self.connection.lock().expect("Failed to get database");
+        let transaction = connection.transaction()?;
+
+        {
+            let mut statement =
+                transaction.prepare("DELETE FROM active_members WHERE student_id = ?1")?;
+
+            for student_id in student_ids {
+                statement.execute(params![student_id])?;
+            }
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    async fn record_sync(&self, sync: &SyncRecord) -> Result<(), Error> {
+        let connection = self.connection.lock().expect("Failed to get database");
+
+        connection.execute(
+            "INSERT INTO membership_syncs (source, added, updated, removed, synced_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                sync.source,
+                sync.added,
+                sync.updated,
+                sync.removed,
+                sync.synced_at
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    async fn last_sync(&self) -> Result<Option<SyncRecord>, Error> {
+        let connection = self.connection.lock().expect("Failed to get database");
+
+        let sync = connection
+            .query_row(
+                "SELECT source, added, updated, removed, synced_at FROM membership_syncs
+                ORDER BY synced_at DESC LIMIT 1",
+                [],
+                |row| {
+                    Ok(SyncRecord {
+                        source: row.get("source")?,
+                        added: row.get("added")?,
+                        updated: row.get("updated")?,
+                        removed: row.get("removed")?,
+                        synced_at: row.get("synced_at")?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(sync)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, NaiveDate, Utc};
+
+    fn member(student_id: &str, full_name: &str) -> StudentRow {
+        StudentRow {
+            full_name: full_name.to_string(),
+            student_id: student_id.to_string(),
+            membership_type: Some("member".to_string()),
+            expires_at: NaiveDate::from_ymd_opt(2099, 12, 31),
+        }
+    }
+
+    fn sync(source: &str, synced_at: chrono::DateTime<Utc>) -> SyncRecord {
+        SyncRecord {
+            source: source.to_string(),
+            added: 1,
+            updated: 2,
+            removed: 3,
+            synced_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn finds_upserted_members() {
+        let directory = SqliteDirectory::open(":memory:", None).unwrap();
+        directory
+            .upsert_members(&[member("1", "John Doe"), member("2", "Jane Citizen")])
+            .await
+            .unwrap();
+        directory
+            .upsert_members(&[member("1", "John Smith")])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            directory.find_member("1").await.unwrap(),
+            Some(member("1", "John Smith"))
+        );
+        assert_eq!(directory.find_member("3").await.unwrap(), None);
+
+        let ids = ["2".to_string(), "3".to_string()];
+        assert_eq!(
+            directory.find_members(&ids).await.unwrap(),
+            [member("2", "Jane Citizen")]
+        );
+        assert!(directory.find_members(&[]).await.unwrap().is_empty());
+        assert_eq!(directory.list_members().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn removes_members() {
+        let directory = SqliteDirectory::open(":memory:", None).unwrap();
+        directory
+            .upsert_members(&[member("1", "John Doe"), member("2", "Jane Citizen")])
+            .await
+            .unwrap();
+
+        directory
+            .remove_members(&["1".to_string(), "3".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(directory.find_member("1").await.unwrap(), None);
+        assert_eq!(
+            directory.list_members().await.unwrap(),
+            [member("2", "Jane Citizen")]
+        );
+    }
+
+    #[tokio::test]
+    async fn returns_the_latest_sync() {
+        let directory = SqliteDirectory::open(":memory:", None).unwrap();
+        assert!(directory.last_sync().await.unwrap().is_none());
+
+        let now = Utc::now();
+        directory.record_sync(&sync("import", now)).await.unwrap();
+        directory
+            .record_sync(&sync("push", now - Duration::hours(1)))
+            .await
+            .unwrap();
+
+        let last = directory.last_sync().await.unwrap().unwrap();
+        assert_eq!(last.source, "import");
+        assert_eq!((last.added, last.updated, last.removed), (1, 2, 3));
+        assert_eq!(last.synced_at, now);
+    }
+}
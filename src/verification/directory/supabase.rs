@@ -0,0 +1,157 @@
+use async_trait::async_trait;
+use supabase::{Client, types::OrderDirection};
+use tracing::instrument;
+
+use crate::{
+    Error,
+    commands::verification::StudentRow,
+    verification::directory::{MemberDirectory, SyncRecord},
+};
+
+const MEMBERS_TABLE: &str = "active_members";
+const SYNCS_TABLE: &str = "membership_syncs";
+const MEMBER_COLUMNS: &str = "full_name, student_id, membership_type, expires_at";
+
+// ids per `in` query, keeps the request URL short
+const QUERY_CHUNK_SIZE: usize = 100;
+// PostgREST returns at most 1000 rows per request by default
+const PAGE_SIZE: u32 = 1000;
+// rows per upsert request
+const WRITE_CHUNK_SIZE: usize = 500;
+
+/// The club's `active_members` table in Supabase
+#[derive(Debug)]
+pub struct SupabaseDirectory {
+    supabase: Client,
+}
+
+impl SupabaseDirectory {
+    pub fn new(supabase: Client) -> Self {
+        Self { supabase }
+    }
+}
+
+#[async_trait]
+impl MemberDirectory for SupabaseDirectory {
+    #[instrument(name = "supabase", skip_all, fields(table = MEMBERS_TABLE, query = "find_member"))]
+    async fn find_member(&self, student_id: &str) -> Result<Option<StudentRow>, Error> {
+        let rows: Vec<StudentRow> = self
+            .supabase
+            .database()
+            .from(MEMBERS_TABLE)
+            .select(MEMBER_COLUMNS)
+            .eq("student_id", student_id)
+            .execute()
+            .await?;
+
+        Ok(rows.into_iter().next())
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = MEMBERS_TABLE, query = "find_members"))]
+    async fn find_members(&self, student_ids: &[String]) -> Result<Vec<StudentRow>, Error> {
+        let mut members = Vec::new();
+
+        for chunk in student_ids.chunks(QUERY_CHUNK_SIZE) {
+            let chunk: Vec<&str> = chunk.iter().map(String::as_str).collect();
+
+            let rows: Vec<StudentRow> = self
+                .supabase
+                .database()
+                .from(MEMBERS_TABLE)
+                .select(MEMBER_COLUMNS)
+                .r#in("student_id", &chunk)
+                .execute()
+                .await?;
+
+            members.extend(rows);
+        }
+
+        Ok(members)
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = MEMBERS_TABLE, query = "list_members"))]
+    async fn list_members(&self) -> Result<Vec<StudentRow>, Error> {
+        let mut members = Vec::new();
+
+        loop {
+            let page: Vec<StudentRow> = self
+                .supabase
+                .database()
+                .from(MEMBERS_TABLE)
+                .select(MEMBER_COLUMNS)
+                .order("student_id", OrderDirection::Ascending)
+                .limit(PAGE_SIZE)
+                .offset(members.len() as u32)
+                .execute()
+                .await?;
+
+            let page_len = page.len();
+            members.extend(page);
+
+            if page_len < PAGE_SIZE as usize {
+                break;
+            }
+        }
+
+        Ok(members)
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = MEMBERS_TABLE, query = "upsert_members"))]
+    async fn upsert_members(&self, members: &[StudentRow]) -> Result<(), Error> {
+        for chunk in members.chunks(WRITE_CHUNK_SIZE) {
+            self.supabase
+                .database()
+                .upsert(MEMBERS_TABLE)
+                .values(chunk)?
+                .returning(MEMBER_COLUMNS)
+                .execute::<StudentRow>()
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = MEMBERS_TABLE, query = "remove_members"))]
+    async fn remove_members(&self, student_ids: &[String]) -> Result<(), Error> {
+        // the delete builder only supports `eq` filters, so each ID is its own request
+        for student_id in student_ids {
+            self.supabase
+                .database()
+                .delete(MEMBERS_TABLE)
+                .eq("student_id", student_id)
+                .returning(MEMBER_COLUMNS)
+                .execute::<StudentRow>()
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = SYNCS_TABLE, query = "record_sync"))]
+    async fn record_sync(&self, sync: &SyncRecord) -> Result<(), Error> {
+        self.supabase
+            .database()
+            .insert(SYNCS_TABLE)
+            .values(sync)?
+            .returning("*")
+            .execute::<SyncRecord>()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = SYNCS_TABLE, query = "last_sync"))]
+    async fn last_sync(&self) -> Result<Option<SyncRecord>, Error> {
+        let syncs: Vec<SyncRecord> = self
+            .supabase
+            .database()
+            .from(SYNCS_TABLE)
+            .select("*")
+            .order("synced_at", OrderDirection::Descending)
+            .limit(1)
+            .execute()
+            .await?;
+
+        Ok(syncs.into_iter().next())
+    }
+}
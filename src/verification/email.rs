@@ -0,0 +1,446 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor, message::Mailbox,
+    transport::smtp::authentication::Credentials,
+};
+use poise::serenity_prelude::{RoleId, UserId};
+use rand::Rng;
+
+use crate::{Error, config::Secret};
+
+/// custom_id of the panel button that grants the default verified role by email
+pub const EMAIL_BUTTON_ID: &str = "verify_email";
+// panels for other roles put the role ID after the prefix, e.g. "verify_email:123"
+const EMAIL_ROLE_PREFIX: &str = "verify_email:";
+/// custom_id of the button that opens the code modal
+pub const CODE_BUTTON_ID: &str = "verify_email_code";
+
+// a member has to wait this long before asking for another code
+const RESEND_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// custom_id of the email button for a panel, `None` meaning the default verified role
+pub fn button_id(role_id: Option<RoleId>) -> String {
+    match role_id {
+        Some(role_id) => format!("{}{}", EMAIL_ROLE_PREFIX, role_id),
+        None => EMAIL_BUTTON_ID.to_string(),
+    }
+}
+
+/// Parses an email button's custom_id, returning the role the panel grants (`None` for the default)
+pub fn parse_button_id(custom_id: &str) -> Option<Option<RoleId>> {
+    if custom_id == EMAIL_BUTTON_ID {
+        return Some(None);
+    }
+
+    let role_id: u64 = custom_id.strip_prefix(EMAIL_ROLE_PREFIX)?.parse().ok()?;
+
+    // RoleId::new panics on zero
+    (role_id != 0).then(|| Some(RoleId::new(role_id)))
+}
+
+/// How the connection to the SMTP server is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// plain SMTP, for a local stand-in such as Mailpit
+    None,
+    StartTls,
+    Tls,
+}
+
+impl SmtpSecurity {
+    pub fn default_port(&self) -> u16 {
+        match self {
+            SmtpSecurity::None => 25,
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Tls => 465,
+        }
+    }
+}
+
+impl FromStr for SmtpSecurity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "none" => Ok(SmtpSecurity::None),
+            "starttls" => Ok(SmtpSecurity::StartTls),
+            "tls" => Ok(SmtpSecurity::Tls),
+            other => Err(format!("unknown SMTP security \"{}\"", other)),
+        }
+    }
+}
+
+/// Settings for email verification
+#[derive(Debug, Clone)]
+pub struct EmailConfig {
+    /// offer verification by email alongside the student ID check
+    pub enabled: bool,
+    /// the only domain codes are sent to
+    pub domain: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_security: SmtpSecurity,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<Secret>,
+    pub from: Option<Mailbox>,
+    /// how long a code can be used for
+    pub code_ttl: Duration,
+    /// wrong codes allowed before the code is thrown away
+    pub max_attempts: u32,
+}
+
+/// Why an email address was rejected, displayed to the member as-is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmailAddressError {
+    Invalid,
+    WrongDomain(String),
+}
+
+impl fmt::Display for EmailAddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmailAddressError::Invalid => write!(f, "That doesn't look like an email address."),
+            EmailAddressError::WrongDomain(domain) => {
+                write!(
+                    f,
+                    "Use your university email address, ending in @{}.",
+                    domain
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for EmailAddressError {}
+
+/// Normalises an email address, checking it belongs to `domain`
+pub fn parse_address(input: &str, domain: &str) -> Result<String, EmailAddressError> {
+    let address = input.trim().to_lowercase();
+
+    let Some((local, address_domain)) = address.split_once('@') else {
+        return Err(EmailAddressError::Invalid);
+    };
+
+    let valid_local = !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '+'));
+
+    if !valid_local || address_domain.is_empty() {
+        return Err(EmailAddressError::Invalid);
+    }
+
+    if address_domain != domain {
+        return Err(EmailAddressError::WrongDomain(domain.to_string()));
+    }
+
+    Ok(address)
+}
+
+/// The mailbox an address delivers to, without any "+tag", so one inbox can't verify twice
+pub fn mailbox(address: &str) -> String {
+    match address.split_once('@') {
+        Some((local, domain)) => {
+            let local = local.split_once('+').map_or(local, |(local, _)| local);
+            format!("{}@{}", local, domain)
+        }
+        None => address.to_string(),
+    }
+}
+
+#[derive(Debug)]
+struct PendingCode {
+    email: String,
+    code: String,
+    role_id: Option<RoleId>,
+    sent_at: Instant,
+    attempts: u32,
+}
+
+/// Result of entering a code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodeCheck {
+    Verified {
+        email: String,
+        role_id: Option<RoleId>,
+    },
+    Wrong {
+        remaining: u32,
+    },
+    /// the last attempt was wrong, the code is gone
+    TooManyAttempts {
+        email: String,
+    },
+    Expired,
+    /// no code was sent, or it was already used
+    Missing,
+}
+
+/// Sends one-time codes and keeps them until they're used, expire or run out of attempts
+pub struct EmailCodes {
+    config: EmailConfig,
+    mailer: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    pending: Mutex<HashMap<UserId, PendingCode>>,
+}
+
+impl fmt::Debug for EmailCodes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmailCodes")
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EmailCodes {
+    pub fn new(config: EmailConfig) -> Result<Self, Error> {
+        let mailer = if config.enabled {
+            let builder = match config.smtp_security {
+                SmtpSecurity::None => {
+                    AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host)
+                }
+                SmtpSecurity::StartTls => {
+                    AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?
+                }
+                SmtpSecurity::Tls => {
+                    AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)?
+                }
+            };
+
+            let mut builder = builder.port(config.smtp_port);
+
+            if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password)
+            {
+                builder = builder.credentials(Credentials::new(
+                    username.clone(),
+                    password.expose().to_string(),
+                ));
+            }
+
+            Some(builder.build())
+        } else {
+            None
+        };
+
+        Ok(Self {
+            config,
+            mailer,
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn config(&self) -> &EmailConfig {
+        &self.config
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Creates a code for the user, replacing any earlier one. Fails with how long to wait
+    /// if they were sent a code less than a minute ago.
+    pub fn issue(
+        &self,
+        user_id: UserId,
+        email: &str,
+        role_id: Option<RoleId>,
+    ) -> Result<String, Duration> {
+        let mut pending = self.pending.lock().expect("Failed to get email codes");
+        pending.retain(|_, code| code.sent_at.elapsed() < self.config.code_ttl);
+
+        if let Some(code) = pending.get(&user_id)
+            && code.sent_at.elapsed() < RESEND_COOLDOWN
+        {
+            return Err(RESEND_COOLDOWN - code.sent_at.elapsed());
+        }
+
+        let code = format!("{:06}", rand::rng().random_range(0..1_000_000));
+
+        pending.insert(
+            user_id,
+            PendingCode {
+                email: email.to_string(),
+                code: code.clone(),
+                role_id,
+                sent_at: Instant::now(),
+                attempts: 0,
+            },
+        );
+
+        Ok(code)
+    }
+
+    /// Forgets the user's code, e.g. when it couldn't be sent
+    pub fn cancel(&self, user_id: UserId) {
+        let mut pending = self.pending.lock().expect("Failed to get email codes");
+        pending.remove(&user_id);
+    }
+
+    pub fn check(&self, user_id: UserId, code: &str) -> CodeCheck {
+        let mut pending = self.pending.lock().expect("Failed to get email codes");
+
+        let Some(entry) = pending.get_mut(&user_id) else {
+            return CodeCheck::Missing;
+        };
+
+        if entry.sent_at.elapsed() >= self.config.code_ttl {
+            pending.remove(&user_id);
+            return CodeCheck::Expired;
+        }
+
+        if entry.code == code.trim() {
+            let entry = pending.remove(&user_id).expect("code was just found");
+
+            return CodeCheck::Verified {
+                email: entry.email,
+                role_id: entry.role_id,
+            };
+        }
+
+        entry.attempts += 1;
+
+        if entry.attempts >= self.config.max_attempts {
+            let entry = pending.remove(&user_id).expect("code was just found");
+
+            return CodeCheck::TooManyAttempts { email: entry.email };
+        }
+
+        CodeCheck::Wrong {
+            remaining: self.config.max_attempts - entry.attempts,
+        }
+    }
+
+    /// Emails the code to the address
+    pub async fn send(&self, email: &str, code: &str) -> Result<(), Error> {
+        let (Some(mailer), Some(from)) = (&self.mailer, &self.config.from) else {
+            return Err("Email verification is not enabled".into());
+        };
+
+        let minutes = self.config.code_ttl.as_secs().div_ceil(60);
+
+        let message = Message::builder()
+            .from(from.clone())
+            .to(email.parse()?)
+            .subject(format!("Your DSEC verification code: {}", code))
+            .body(format!(
+                "Your DSEC Discord verification code is {}.\n\nIt expires in {} minutes. If you didn't ask for it, you can ignore this email.\n",
+                code, minutes
+            ))?;
+
+        mailer.send(message).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(max_attempts: u32) -> EmailCodes {
+        EmailCodes::new(EmailConfig {
+            enabled: false,
+            domain: "deakin.edu.au".to_string(),
+            smtp_host: "localhost".to_string(),
+            smtp_port: 1025,
+            smtp_security: SmtpSecurity::None,
+            smtp_username: None,
+            smtp_password: None,
+            from: None,
+            code_ttl: Duration::from_secs(600),
+            max_attempts,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn accepts_only_the_configured_domain() {
+        assert_eq!(
+            parse_address(" John.Doe@Deakin.edu.au ", "deakin.edu.au"),
+            Ok("john.doe@deakin.edu.au".to_string())
+        );
+        assert_eq!(
+            parse_address("john@gmail.com", "deakin.edu.au"),
+            Err(EmailAddressError::WrongDomain("deakin.edu.au".to_string()))
+        );
+        assert_eq!(
+            parse_address("john@evil.deakin.edu.au", "deakin.edu.au"),
+            Err(EmailAddressError::WrongDomain("deakin.edu.au".to_string()))
+        );
+        assert_eq!(
+            parse_address("john doe@deakin.edu.au", "deakin.edu.au"),
+            Err(EmailAddressError::Invalid)
+        );
+        assert_eq!(
+            parse_address("deakin.edu.au", "deakin.edu.au"),
+            Err(EmailAddressError::Invalid)
+        );
+    }
+
+    #[test]
+    fn mailbox_drops_tags() {
+        assert_eq!(mailbox("john+one@deakin.edu.au"), "john@deakin.edu.au");
+        assert_eq!(mailbox("john.doe@deakin.edu.au"), "john.doe@deakin.edu.au");
+    }
+
+    #[test]
+    fn codes_are_single_use() {
+        let codes = codes(3);
+        let user_id = UserId::new(1);
+
+        let code = codes.issue(user_id, "john@deakin.edu.au", None).unwrap();
+        assert_eq!(code.len(), 6);
+
+        // asking again straight away is refused
+        assert!(codes.issue(user_id, "john@deakin.edu.au", None).is_err());
+
+        assert_eq!(
+            codes.check(user_id, &code),
+            CodeCheck::Verified {
+                email: "john@deakin.edu.au".to_string(),
+                role_id: None,
+            }
+        );
+        assert_eq!(codes.check(user_id, &code), CodeCheck::Missing);
+    }
+
+    #[test]
+    fn wrong_codes_use_up_attempts() {
+        let codes = codes(2);
+        let user_id = UserId::new(1);
+
+        let code = codes
+            .issue(user_id, "john@deakin.edu.au", Some(RoleId::new(5)))
+            .unwrap();
+        let wrong = if code == "000000" { "000001" } else { "000000" };
+
+        assert_eq!(
+            codes.check(user_id, wrong),
+            CodeCheck::Wrong { remaining: 1 }
+        );
+        assert_eq!(
+            codes.check(user_id, wrong),
+            CodeCheck::TooManyAttempts {
+                email: "john@deakin.edu.au".to_string()
+            }
+        );
+        assert_eq!(codes.check(user_id, &code), CodeCheck::Missing);
+    }
+
+    #[test]
+    fn button_ids_round_trip() {
+        assert_eq!(parse_button_id(&button_id(None)), Some(None));
+        assert_eq!(
+            parse_button_id(&button_id(Some(RoleId::new(42)))),
+            Some(Some(RoleId::new(42)))
+        );
+        assert_eq!(parse_button_id(CODE_BUTTON_ID), None);
+        assert_eq!(parse_button_id("verify_email:0"), None);
+    }
+}
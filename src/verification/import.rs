@@ -0,0 +1,639 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude::{
+    ButtonStyle, Colour, CreateActionRow, CreateButton, CreateEmbed, Timestamp, UserId,
+};
+
+use chrono::{NaiveDate, Utc};
+
+use crate::{
+    commands::verification::StudentRow,
+    verification::{directory::SyncRecord, student_id::StudentId},
+};
+
+const CONFIRM_PREFIX: &str = "import_confirm:";
+const CANCEL_PREFIX: &str = "import_cancel:";
+
+// how long a preview can still be confirmed
+const PENDING_TTL: Duration = Duration::from_secs(15 * 60);
+// most members or errors shown per list in the preview
+const PREVIEW_LIST_LIMIT: usize = 10;
+
+// header names from the signup platform's exports, compared lowercase with only letters and digits.
+// earlier names are preferred, so the generic ones come last.
+const FULL_NAME_HEADERS: [&str; 3] = ["fullname", "membername", "name"];
+const FIRST_NAME_HEADERS: [&str; 3] = ["firstname", "givenname", "preferredfirstname"];
+const LAST_NAME_HEADERS: [&str; 3] = ["lastname", "surname", "familyname"];
+const STUDENT_ID_HEADERS: [&str; 4] = ["studentid", "studentnumber", "studentno", "id"];
+const MEMBERSHIP_TYPE_HEADERS: [&str; 4] = ["membershiptype", "membership", "tier", "type"];
+const EXPIRY_HEADERS: [&str; 5] = ["expiry", "expires", "expiresat", "expirydate", "enddate"];
+
+/// A row of the CSV that couldn't be imported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportRowError {
+    /// line in the file, starting at 1 for the header
+    pub line: u64,
+    pub message: String,
+}
+
+enum NameColumns {
+    Full(usize),
+    Split { first: usize, last: usize },
+}
+
+fn header_key(header: &str) -> String {
+    header
+        .chars()
+        .filter(|letter| letter.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// signup platform exports use Australian dates
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%d/%m/%Y"))
+        .ok()
+}
+
+fn find_column(headers: &[String], names: &[&str]) -> Option<usize> {
+    names
+        .iter()
+        .find_map(|name| headers.iter().position(|header| header == name))
+}
+
+/// Parses a membership export, which needs a header row with a student ID column and either
+/// a full name column or first and last name columns. Membership type and expiry columns are optional.
+///
+/// Every row is validated, the file is only accepted when all of them are.
+pub fn parse_csv(data: &[u8]) -> Result<Vec<StudentRow>, Vec<ImportRowError>> {
+    let file_error = |message: &str| {
+        vec![ImportRowError {
+            line: 1,
+            message: message.to_string(),
+        }]
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(data);
+
+    let headers: Vec<String> = match reader.headers() {
+        Ok(headers) => headers.iter().map(header_key).collect(),
+        Err(err) => {
+            return Err(file_error(&format!(
+                "Unable to read the header row: {}",
+                err
+            )));
+        }
+    };
+
+    let Some(student_id_column) = find_column(&headers, &STUDENT_ID_HEADERS) else {
+        return Err(file_error(
+            "No student ID column, expected a header like \"Student ID\"",
+        ));
+    };
+
+    let name_columns = match (
+        find_column(&headers, &FULL_NAME_HEADERS),
+        find_column(&headers, &FIRST_NAME_HEADERS),
+        find_column(&headers, &LAST_NAME_HEADERS),
+    ) {
+        // a bare "Name" column loses to first and last name columns
+        (Some(full), Some(first), Some(last)) if headers[full] == "name" => {
+            NameColumns::Split { first, last }
+        }
+        (Some(full), _, _) => NameColumns::Full(full),
+        (None, Some(first), Some(last)) => NameColumns::Split { first, last },
+        _ => {
+            return Err(file_error(
+                "No name column, expected \"Full Name\" or \"First Name\" and \"Last Name\"",
+            ));
+        }
+    };
+
+    let membership_type_column = find_column(&headers, &MEMBERSHIP_TYPE_HEADERS);
+    let expiry_column = find_column(&headers, &EXPIRY_HEADERS);
+
+    let mut members = Vec::new();
+    let mut errors = Vec::new();
+    // line each student ID was first seen on
+    let mut seen: HashMap<String, u64> = HashMap::new();
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                errors.push(ImportRowError {
+                    line: err.position().map(|position| position.line()).unwrap_or(0),
+                    message: err.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let line = record
+            .position()
+            .map(|position| position.line())
+            .unwrap_or(0);
+
+        // spreadsheets often end with blank rows
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+
+        let field = |column: usize| record.get(column).unwrap_or_default();
+
+        let student_id = match field(student_id_column).parse::<StudentId>() {
+            Ok(student_id) => student_id,
+            Err(err) => {
+                errors.push(ImportRowError {
+                    line,
+                    message: format!("\"{}\": {}", field(student_id_column), err),
+                });
+                continue;
+            }
+        };
+
+        let full_name = match name_columns {
+            NameColumns::Full(column) => field(column).to_string(),
+            NameColumns::Split { first, last } => format!("{} {}", field(first), field(last))
+                .trim()
+                .to_string(),
+        };
+
+        if full_name.is_empty() {
+            errors.push(ImportRowError {
+                line,
+                message: format!("{} has no name", student_id),
+            });
+            continue;
+        }
+
+        let expires_at = match expiry_column.map(field).filter(|value| !value.is_empty()) {
+            None => None,
+            Some(value) => match parse_date(value) {
+                Some(expires_at) => Some(expires_at),
+                None => {
+                    errors.push(ImportRowError {
+                        line,
+                        message: format!(
+                            "\"{}\" is not a date, use YYYY-MM-DD or DD/MM/YYYY",
+                            value
+                        ),
+                    });
+                    continue;
+                }
+            },
+        };
+
+        if let Some(first_line) = seen.insert(student_id.as_str().to_string(), line) {
+            errors.push(ImportRowError {
+                line,
+                message: format!("{} is also on line {}", student_id, first_line),
+            });
+            continue;
+        }
+
+        members.push(StudentRow {
+            full_name,
+            student_id: student_id.as_str().to_string(),
+            membership_type: membership_type_column
+                .map(field)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string),
+            expires_at,
+        });
+    }
+
+    if errors.is_empty() {
+        Ok(members)
+    } else {
+        Err(errors)
+    }
+}
+
+/// A member whose row changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberChange {
+    pub old: StudentRow,
+    pub new: StudentRow,
+}
+
+fn optional<T: ToString>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map(T::to_string)
+        .unwrap_or("none".to_string())
+}
+
+impl MemberChange {
+    /// e.g. "s123456789 name: Jon Doe → John Doe, type: member → life"
+    fn describe(&self) -> String {
+        let mut changes = Vec::new();
+
+        if self.old.full_name != self.new.full_name {
+            changes.push(format!(
+                "name: {} → {}",
+                self.old.full_name, self.new.full_name
+            ));
+        }
+
+        if self.old.membership_type != self.new.membership_type {
+            changes.push(format!(
+                "type: {} → {}",
+                optional(&self.old.membership_type),
+                optional(&self.new.membership_type)
+            ));
+        }
+
+        if self.old.expires_at != self.new.expires_at {
+            changes.push(format!(
+                "expiry: {} → {}",
+                optional(&self.old.expires_at),
+                optional(&self.new.expires_at)
+            ));
+        }
+
+        format!("s{} {}", self.new.student_id, changes.join(", "))
+    }
+}
+
+/// How an import would change `active_members`
+#[derive(Debug, Clone, Default)]
+pub struct ImportDiff {
+    pub added: Vec<StudentRow>,
+    pub updated: Vec<MemberChange>,
+    /// members missing from the file, empty unless missing members are being removed
+    pub removed: Vec<StudentRow>,
+    pub unchanged: usize,
+}
+
+impl ImportDiff {
+    /// Compares the imported members with the current ones
+    pub fn new(current: Vec<StudentRow>, imported: Vec<StudentRow>, remove_missing: bool) -> Self {
+        let mut current: HashMap<String, StudentRow> = current
+            .into_iter()
+            .map(|member| (member.student_id.clone(), member))
+            .collect();
+
+        let mut diff = ImportDiff::default();
+
+        for member in imported {
+            match current.remove(&member.student_id) {
+                None => diff.added.push(member),
+                Some(old) if old == member => diff.unchanged += 1,
+                Some(old) => diff.updated.push(MemberChange { old, new: member }),
+            }
+        }
+
+        if remove_missing {
+            diff.removed = current.into_values().collect();
+            diff.removed
+                .sort_by(|left, right| left.student_id.cmp(&right.student_id));
+        }
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+
+    /// Rows to upsert, new and changed members
+    pub fn upserts(&self) -> Vec<StudentRow> {
+        let updated = self.updated.iter().map(|change| change.new.clone());
+
+        self.added.iter().cloned().chain(updated).collect()
+    }
+
+    /// Student IDs to remove
+    pub fn removals(&self) -> Vec<String> {
+        self.removed
+            .iter()
+            .map(|member| member.student_id.clone())
+            .collect()
+    }
+
+    /// Every student ID the import touches, for cache invalidation
+    pub fn affected_ids(&self) -> HashSet<String> {
+        self.upserts()
+            .into_iter()
+            .map(|member| member.student_id)
+            .chain(self.removals())
+            .collect()
+    }
+
+    pub fn to_sync_record(&self) -> SyncRecord {
+        SyncRecord {
+            source: "import".to_string(),
+            added: self.added.len() as i64,
+            updated: self.updated.len() as i64,
+            removed: self.removed.len() as i64,
+            synced_at: Utc::now(),
+        }
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "**{}** added, **{}** updated, **{}** removed, **{}** unchanged.",
+            self.added.len(),
+            self.updated.len(),
+            self.removed.len(),
+            self.unchanged
+        )
+    }
+
+    /// Preview shown before the import is confirmed
+    pub fn to_embed(&self) -> CreateEmbed {
+        let mut embed = CreateEmbed::new()
+            .title("Membership import preview 📋")
+            .description(self.summary())
+            .colour(Colour::BLURPLE);
+
+        if !self.added.is_empty() {
+            embed = embed.field(
+                format!("Added ({})", self.added.len()),
+                preview_list(&self.added, |member| {
+                    format!("s{} {}", member.student_id, member.full_name)
+                }),
+                false,
+            );
+        }
+
+        if !self.updated.is_empty() {
+            embed = embed.field(
+                format!("Updated ({})", self.updated.len()),
+                preview_list(&self.updated, MemberChange::describe),
+                false,
+            );
+        }
+
+        if !self.removed.is_empty() {
+            embed = embed.field(
+                format!("Removed ({})", self.removed.len()),
+                preview_list(&self.removed, |member| {
+                    format!("s{} {}", member.student_id, member.full_name)
+                }),
+                false,
+            );
+        }
+
+        embed
+    }
+
+    /// Summary posted to the mod-log channel once the import is applied
+    pub fn to_applied_embed(&self, author: UserId) -> CreateEmbed {
+        CreateEmbed::new()
+            .title("Membership import applied 📋")
+            .description(format!("{}\n\nImported by <@{}>", self.summary(), author))
+            .colour(Colour::DARK_GREEN)
+            .timestamp(Timestamp::now())
+    }
+}
+
+fn preview_list<T>(items: &[T], line: impl Fn(&T) -> String) -> String {
+    let mut lines: Vec<String> = items.iter().take(PREVIEW_LIST_LIMIT).map(line).collect();
+
+    if items.len() > PREVIEW_LIST_LIMIT {
+        lines.push(format!("…and {} more", items.len() - PREVIEW_LIST_LIMIT));
+    }
+
+    // keep the field under Discord's 1024 character limit
+    let mut value = lines.join("\n");
+    if value.chars().count() > 1024 {
+        value = value.chars().take(1020).collect::<String>() + " …";
+    }
+
+    value
+}
+
+/// Embed listing why a file was rejected
+pub fn errors_embed(errors: &[ImportRowError]) -> CreateEmbed {
+    CreateEmbed::new()
+        .title("Membership import rejected ❌")
+        .description(format!(
+            "{} rows couldn't be imported, nothing was changed. Fix them and upload the file again.",
+            errors.len()
+        ))
+        .field(
+            "Errors",
+            preview_list(errors, |error| format!("Line {}: {}", error.line, error.message)),
+            false,
+        )
+        .colour(Colour::RED)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportDecision {
+    Confirm,
+    Cancel,
+}
+
+/// Parses the custom_id of a Confirm/Cancel button into the decision and pending import ID
+pub fn parse_button(custom_id: &str) -> Option<(ImportDecision, u64)> {
+    let (decision, id) = if let Some(id) = custom_id.strip_prefix(CONFIRM_PREFIX) {
+        (ImportDecision::Confirm, id)
+    } else if let Some(id) = custom_id.strip_prefix(CANCEL_PREFIX) {
+        (ImportDecision::Cancel, id)
+    } else {
+        return None;
+    };
+
+    Some((decision, id.parse().ok()?))
+}
+
+#[derive(Debug)]
+pub struct PendingImport {
+    /// the committee member who uploaded the file
+    pub author: UserId,
+    pub diff: ImportDiff,
+    created: Instant,
+}
+
+/// Holds previewed imports until they are confirmed or cancelled
+#[derive(Debug, Default)]
+pub struct ImportQueue {
+    pending: Mutex<HashMap<u64, PendingImport>>,
+    next_id: AtomicU64,
+}
+
+impl ImportQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps the import and returns the Confirm/Cancel buttons for its preview
+    pub fn add(&self, author: UserId, diff: ImportDiff) -> CreateActionRow {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        let mut pending = self.pending.lock().expect("Failed to get import queue");
+        pending.retain(|_, import| import.created.elapsed() < PENDING_TTL);
+        pending.insert(
+            id,
+            PendingImport {
+                author,
+                diff,
+                created: Instant::now(),
+            },
+        );
+
+        let confirm = CreateButton::new(format!("{}{}", CONFIRM_PREFIX, id))
+            .label("Confirm import")
+            .style(ButtonStyle::Success);
+
+        let cancel = CreateButton::new(format!("{}{}", CANCEL_PREFIX, id))
+            .label("Cancel")
+            .style(ButtonStyle::Secondary);
+
+        CreateActionRow::Buttons(vec![confirm, cancel])
+    }
+
+    /// Takes the pending import, if `author` uploaded it and it hasn't expired
+    pub fn take(&self, id: u64, author: UserId) -> Option<PendingImport> {
+        let mut pending = self.pending.lock().expect("Failed to get import queue");
+
+        // someone else's button press leaves the import for its author
+        if pending.get(&id)?.author != author {
+            return None;
+        }
+
+        pending
+            .remove(&id)
+            .filter(|import| import.created.elapsed() < PENDING_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(student_id: &str, full_name: &str) -> StudentRow {
+        StudentRow {
+            full_name: full_name.to_string(),
+            student_id: student_id.to_string(),
+            membership_type: None,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn parses_full_and_split_name_exports() {
+        let full =
+            "\u{feff}Student ID,Full Name\ns123456789,John Doe\n223456789 , Jane Citizen \n,\n";
+        assert_eq!(
+            parse_csv(full.as_bytes()),
+            Ok(vec![
+                member("123456789", "John Doe"),
+                member("223456789", "Jane Citizen"),
+            ])
+        );
+
+        let split =
+            "First Name,Last Name,Email,Student Number\nJosé,García,jose@example.com,S323456789\n";
+        assert_eq!(
+            parse_csv(split.as_bytes()),
+            Ok(vec![member("323456789", "José García")])
+        );
+    }
+
+    #[test]
+    fn prefers_specific_headers_over_generic_ones() {
+        let csv = "ID,Name,Type,First Name,Last Name,Student ID,Membership Type\n1,Event,Ticket,John,Doe,s123456789,Life\n";
+        let members = parse_csv(csv.as_bytes()).unwrap();
+
+        assert_eq!(members[0].student_id, "123456789");
+        assert_eq!(members[0].full_name, "John Doe");
+        assert_eq!(members[0].membership_type.as_deref(), Some("Life"));
+
+        let generic = "Name,ID,Type\nJohn Doe,s123456789,member\n";
+        let members = parse_csv(generic.as_bytes()).unwrap();
+        assert_eq!(members[0].full_name, "John Doe");
+        assert_eq!(members[0].membership_type.as_deref(), Some("member"));
+    }
+
+    #[test]
+    fn parses_membership_type_and_expiry() {
+        let csv = "Student ID,Full Name,Membership Type,Expiry Date\ns123456789,John Doe,Life,\ns223456789,Jane Citizen,member,31/12/2026\ns323456789,Jo Bloggs,,2027-06-30\ns423456789,Bad Date,member,next year\n";
+        let errors = parse_csv(csv.as_bytes()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 5);
+
+        let valid: String = csv
+            .lines()
+            .take(4)
+            .map(|line| format!("{}\n", line))
+            .collect();
+        let members = parse_csv(valid.as_bytes()).unwrap();
+
+        assert_eq!(members[0].membership_type.as_deref(), Some("Life"));
+        assert_eq!(members[0].expires_at, None);
+        assert_eq!(members[1].expires_at, NaiveDate::from_ymd_opt(2026, 12, 31));
+        assert_eq!(members[2].membership_type, None);
+        assert_eq!(members[2].expires_at, NaiveDate::from_ymd_opt(2027, 6, 30));
+    }
+
+    #[test]
+    fn reports_every_invalid_row() {
+        let csv = "Student ID,Full Name\ns123456789,John Doe\nabc,Jane\ns223456789,\ns123456789,John Again\n";
+        let errors = parse_csv(csv.as_bytes()).unwrap_err();
+
+        let lines: Vec<u64> = errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, vec![3, 4, 5]);
+
+        let missing_column = parse_csv("Email,Full Name\na@b.c,John\n".as_bytes()).unwrap_err();
+        assert_eq!(missing_column[0].line, 1);
+    }
+
+    #[test]
+    fn diff_finds_added_updated_and_removed_members() {
+        let current = vec![
+            member("1", "Same Name"),
+            member("2", "Old Name"),
+            member("3", "Gone Member"),
+        ];
+        let imported = vec![
+            member("1", "Same Name"),
+            member("2", "New Name"),
+            member("4", "New Member"),
+        ];
+
+        let diff = ImportDiff::new(current.clone(), imported.clone(), true);
+        assert_eq!(diff.added, vec![member("4", "New Member")]);
+        assert_eq!(
+            diff.updated,
+            vec![MemberChange {
+                old: member("2", "Old Name"),
+                new: member("2", "New Name"),
+            }]
+        );
+        assert_eq!(diff.removed, vec![member("3", "Gone Member")]);
+        assert_eq!(diff.unchanged, 1);
+
+        let mut affected: Vec<String> = diff.affected_ids().into_iter().collect();
+        affected.sort();
+        assert_eq!(affected, vec!["2", "3", "4"]);
+
+        let kept = ImportDiff::new(current, imported, false);
+        assert!(kept.removed.is_empty());
+    }
+
+    #[test]
+    fn only_the_author_can_take_an_import() {
+        let queue = ImportQueue::new();
+        let diff = ImportDiff::new(Vec::new(), vec![member("1", "New Member")], false);
+        let _buttons = queue.add(UserId::new(1), diff);
+
+        assert!(queue.take(0, UserId::new(2)).is_none());
+        assert!(queue.take(0, UserId::new(1)).is_some());
+        assert!(queue.take(0, UserId::new(1)).is_none());
+    }
+}
@@ -0,0 +1,308 @@
+use poise::serenity_prelude::{Timestamp, UserId};
+use serde::{Deserialize, Serialize};
+use supabase::Client;
+use tracing::instrument;
+
+const LINKS_TABLE: &str = "student_links";
+const EMAIL_LINKS_TABLE: &str = "email_links";
+
+/// The Discord account a student ID was verified with, one row per student ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudentLink {
+    pub student_id: String,
+    pub discord_user_id: UserId,
+    pub linked_at: Timestamp,
+}
+
+/// Reads and writes the `student_links` table
+#[derive(Debug)]
+pub struct LinkStore {
+    supabase: Client,
+}
+
+impl LinkStore {
+    pub fn new(supabase: Client) -> Self {
+        Self { supabase }
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = LINKS_TABLE, query = "find_by_student"))]
+    pub async fn find_by_student(&self, student_id: &str) -> supabase::Result<Option<StudentLink>> {
+        let links: Vec<StudentLink> = self
+            .supabase
+            .database()
+            .from(LINKS_TABLE)
+            .select("*")
+            .eq("student_id", student_id)
+            .execute()
+            .await?;
+
+        Ok(links.into_iter().next())
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = LINKS_TABLE, query = "find_by_user"))]
+    pub async fn find_by_user(&self, user_id: UserId) -> supabase::Result<Option<StudentLink>> {
+        let links: Vec<StudentLink> = self
+            .supabase
+            .database()
+            .from(LINKS_TABLE)
+            .select("*")
+            .eq("discord_user_id", &user_id.to_string())
+            .execute()
+            .await?;
+
+        Ok(links.into_iter().next())
+    }
+
+    /// Links for any of the users, users without one are skipped
+    #[instrument(name = "supabase", skip_all, fields(table = LINKS_TABLE, query = "find_by_users"))]
+    pub async fn find_by_users(&self, user_ids: &[UserId]) -> supabase::Result<Vec<StudentLink>> {
+        let mut links = Vec::new();
+
+        // keep each request URL short
+        for chunk in user_ids.chunks(100) {
+            let chunk: Vec<String> = chunk.iter().map(UserId::to_string).collect();
+            let chunk: Vec<&str> = chunk.iter().map(String::as_str).collect();
+
+            let found: Vec<StudentLink> = self
+                .supabase
+                .database()
+                .from(LINKS_TABLE)
+                .select("*")
+                .r#in("discord_user_id", &chunk)
+                .execute()
+                .await?;
+
+            links.extend(found);
+        }
+
+        Ok(links)
+    }
+
+    /// Links the student ID to the user, replacing any link the user already had.
+    /// Returns `None` if another account already holds the student ID.
+    #[instrument(name = "supabase", skip_all, fields(table = LINKS_TABLE, query = "link"))]
+    pub async fn link(
+        &self,
+        student_id: &str,
+        user_id: UserId,
+    ) -> supabase::Result<Option<StudentLink>> {
+        let previous = self.find_by_user(user_id).await?;
+
+        if let Some(previous) = &previous
+            && previous.student_id == student_id
+        {
+            return Ok(Some(previous.clone()));
+        }
+
+        // a Discord account can only hold one student ID at a time
+        if previous.is_some() {
+            self.clear_user(user_id).await?;
+        }
+
+        let link = StudentLink {
+            student_id: student_id.to_string(),
+            discord_user_id: user_id,
+            linked_at: Timestamp::now(),
+        };
+
+        // a plain insert, so two accounts racing for one student ID can't both win
+        match self.insert(&link).await {
+            Ok(()) => Ok(Some(link)),
+            Err(err) if is_unique_violation(&err) => {
+                if let Some(previous) = previous
+                    && let Err(err) = self.insert(&previous).await
+                {
+                    tracing::warn!(error = %err, "Failed to restore the previous student ID link");
+                }
+
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn insert(&self, link: &StudentLink) -> supabase::Result<()> {
+        self.supabase
+            .database()
+            .insert(LINKS_TABLE)
+            .values(link)?
+            .returning("*")
+            .execute::<StudentLink>()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Removes the link for the student ID, returning it if there was one
+    #[instrument(name = "supabase", skip_all, fields(table = LINKS_TABLE, query = "clear"))]
+    pub async fn clear(&self, student_id: &str) -> supabase::Result<Option<StudentLink>> {
+        let removed: Vec<StudentLink> = self
+            .supabase
+            .database()
+            .delete(LINKS_TABLE)
+            .eq("student_id", student_id)
+            .returning("*")
+            .execute()
+            .await?;
+
+        Ok(removed.into_iter().next())
+    }
+
+    /// Removes the user's link, returning it if there was one
+    #[instrument(name = "supabase", skip_all, fields(table = LINKS_TABLE, query = "clear_user"))]
+    pub async fn clear_user(&self, user_id: UserId) -> supabase::Result<Option<StudentLink>> {
+        let removed: Vec<StudentLink> = self
+            .supabase
+            .database()
+            .delete(LINKS_TABLE)
+            .eq("discord_user_id", &user_id.to_string())
+            .returning("*")
+            .execute()
+            .await?;
+
+        Ok(removed.into_iter().next())
+    }
+}
+
+/// The Discord account an email address was verified with, one row per address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailLink {
+    /// the address as returned by `email::mailbox`
+    pub email: String,
+    pub discord_user_id: UserId,
+    pub linked_at: Timestamp,
+}
+
+/// Reads and writes the `email_links` table
+#[derive(Debug)]
+pub struct EmailLinkStore {
+    supabase: Client,
+}
+
+impl EmailLinkStore {
+    pub fn new(supabase: Client) -> Self {
+        Self { supabase }
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = EMAIL_LINKS_TABLE, query = "find_by_email"))]
+    pub async fn find_by_email(&self, email: &str) -> supabase::Result<Option<EmailLink>> {
+        let links: Vec<EmailLink> = self
+            .supabase
+            .database()
+            .from(EMAIL_LINKS_TABLE)
+            .select("*")
+            .eq("email", email)
+            .execute()
+            .await?;
+
+        Ok(links.into_iter().next())
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = EMAIL_LINKS_TABLE, query = "find_by_user"))]
+    pub async fn find_by_user(&self, user_id: UserId) -> supabase::Result<Option<EmailLink>> {
+        let links: Vec<EmailLink> = self
+            .supabase
+            .database()
+            .from(EMAIL_LINKS_TABLE)
+            .select("*")
+            .eq("discord_user_id", &user_id.to_string())
+            .execute()
+            .await?;
+
+        Ok(links.into_iter().next())
+    }
+
+    /// Links for any of the users, users without one are skipped
+    #[instrument(name = "supabase", skip_all, fields(table = EMAIL_LINKS_TABLE, query = "find_by_users"))]
+    pub async fn find_by_users(&self, user_ids: &[UserId]) -> supabase::Result<Vec<EmailLink>> {
+        let mut links = Vec::new();
+
+        // keep each request URL short
+        for chunk in user_ids.chunks(100) {
+            let chunk: Vec<String> = chunk.iter().map(UserId::to_string).collect();
+            let chunk: Vec<&str> = chunk.iter().map(String::as_str).collect();
+
+            let found: Vec<EmailLink> = self
+                .supabase
+                .database()
+                .from(EMAIL_LINKS_TABLE)
+                .select("*")
+                .r#in("discord_user_id", &chunk)
+                .execute()
+                .await?;
+
+            links.extend(found);
+        }
+
+        Ok(links)
+    }
+
+    /// Links the address to the user, replacing any address the user already had.
+    /// Returns `None` if another account already verified with the address.
+    #[instrument(name = "supabase", skip_all, fields(table = EMAIL_LINKS_TABLE, query = "link"))]
+    pub async fn link(&self, email: &str, user_id: UserId) -> supabase::Result<Option<EmailLink>> {
+        let previous = self.find_by_user(user_id).await?;
+
+        if let Some(previous) = &previous
+            && previous.email == email
+        {
+            return Ok(Some(previous.clone()));
+        }
+
+        if previous.is_some() {
+            self.clear_user(user_id).await?;
+        }
+
+        let link = EmailLink {
+            email: email.to_string(),
+            discord_user_id: user_id,
+            linked_at: Timestamp::now(),
+        };
+
+        match self.insert(&link).await {
+            Ok(()) => Ok(Some(link)),
+            Err(err) if is_unique_violation(&err) => {
+                if let Some(previous) = previous
+                    && let Err(err) = self.insert(&previous).await
+                {
+                    tracing::warn!(error = %err, "Failed to restore the previous email link");
+                }
+
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn insert(&self, link: &EmailLink) -> supabase::Result<()> {
+        self.supabase
+            .database()
+            .insert(EMAIL_LINKS_TABLE)
+            .values(link)?
+            .returning("*")
+            .execute::<EmailLink>()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Removes the user's address, returning it if there was one
+    #[instrument(name = "supabase", skip_all, fields(table = EMAIL_LINKS_TABLE, query = "clear_user"))]
+    pub async fn clear_user(&self, user_id: UserId) -> supabase::Result<Option<EmailLink>> {
+        let removed: Vec<EmailLink> = self
+            .supabase
+            .database()
+            .delete(EMAIL_LINKS_TABLE)
+            .eq("discord_user_id", &user_id.to_string())
+            .returning("*")
+            .execute()
+            .await?;
+
+        Ok(removed.into_iter().next())
+    }
+}
+
+// Postgres reports a duplicate primary or unique key as SQLSTATE 23505
+fn is_unique_violation(err: &supabase::Error) -> bool {
+    matches!(err, supabase::Error::Database { message, .. } if message.contains("23505"))
+}
@@ -0,0 +1,195 @@
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+
+// letters that don't decompose into a base letter plus an accent
+fn fold_letter(letter: char) -> Option<&'static str> {
+    match letter {
+        'ß' => Some("ss"),
+        'æ' => Some("ae"),
+        'œ' => Some("oe"),
+        'ø' => Some("o"),
+        'đ' | 'ð' => Some("d"),
+        'ł' => Some("l"),
+        'þ' => Some("th"),
+        'ı' => Some("i"),
+        _ => None,
+    }
+}
+
+/// Splits a name into lowercase tokens with diacritics removed.
+///
+/// Hyphens, dots and commas separate tokens, apostrophes are dropped ("O'Brien" -> "obrien").
+pub fn normalise(name: &str) -> Vec<String> {
+    let mut folded = String::with_capacity(name.len());
+
+    for letter in name.nfkd().filter(|letter| !is_combining_mark(*letter)) {
+        for letter in letter.to_lowercase() {
+            if let Some(replacement) = fold_letter(letter) {
+                folded.push_str(replacement);
+            } else if letter.is_alphanumeric() {
+                folded.push(letter);
+            } else if matches!(letter, '\'' | '’' | '‘' | '`') {
+                continue;
+            } else {
+                folded.push(' ');
+            }
+        }
+    }
+
+    folded.split_whitespace().map(str::to_string).collect()
+}
+
+/// How well a submitted name matches the name on record, from 0.0 (nothing in common) to 1.0 (same name).
+///
+/// Token order doesn't matter and the record may have extra tokens (middle names) the member left out.
+/// Each submitted token is compared to its closest unused token on record, so padding the submitted
+/// name with extra tokens lowers the score instead of hiding among them.
+pub fn similarity(submitted: &str, on_record: &str) -> f64 {
+    let submitted = normalise(submitted);
+    let on_record = normalise(on_record);
+
+    if submitted.is_empty() || on_record.is_empty() {
+        return 0.0;
+    }
+
+    // "Mary-Jane" vs "Maryjane"
+    if submitted.concat() == on_record.concat() {
+        return 1.0;
+    }
+
+    let mut unused: Vec<&str> = on_record.iter().map(String::as_str).collect();
+    let mut total = 0.0;
+
+    // submitted tokens left over once every token on record is used score nothing
+    for token in &submitted {
+        let best = unused
+            .iter()
+            .enumerate()
+            .map(|(index, candidate)| (index, strsim::jaro_winkler(token, candidate)))
+            .max_by(|(_, left), (_, right)| left.total_cmp(right));
+
+        if let Some((index, score)) = best {
+            total += score;
+            unused.remove(index);
+        }
+    }
+
+    let score = total / submitted.len() as f64;
+
+    // a lone given name shouldn't be enough to match a full name
+    if submitted.len() == 1 && on_record.len() > 1 {
+        score * 0.75
+    } else {
+        score
+    }
+}
+
+/// Decides whether a submitted name matches the name on record
+#[derive(Debug, Clone)]
+pub struct NameMatcher {
+    threshold: f64,
+}
+
+impl NameMatcher {
+    pub fn new(threshold: f64) -> Self {
+        Self { threshold }
+    }
+
+    pub fn matches(&self, submitted: &str, on_record: &str) -> bool {
+        similarity(submitted, on_record) >= self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalise_folds_case_accents_and_punctuation() {
+        assert_eq!(normalise("  José   GARCÍA "), vec!["jose", "garcia"]);
+        assert_eq!(
+            normalise("Mary-Jane O'Brien"),
+            vec!["mary", "jane", "obrien"]
+        );
+        assert_eq!(
+            normalise("Ærøskøbing Straße"),
+            vec!["aeroskobing", "strasse"]
+        );
+        assert_eq!(normalise("Nguyễn Văn Đức"), vec!["nguyen", "van", "duc"]);
+        assert_eq!(normalise("Smith, John"), vec!["smith", "john"]);
+        assert!(normalise(" - ").is_empty());
+    }
+
+    #[test]
+    fn matches_real_world_variants() {
+        let matcher = NameMatcher::new(0.9);
+
+        // (submitted, on record)
+        let variants = [
+            ("John Smith", "John Smith"),
+            ("john smith", "John Smith"),
+            ("John  Smith ", "John Smith"),
+            ("Jose Garcia", "José García"),
+            ("Zoe Lefevre", "Zoë Lefèvre"),
+            ("Mary Jane Watson", "Mary-Jane Watson"),
+            ("Maryjane Watson", "Mary-Jane Watson"),
+            ("Smith John", "John Smith"),
+            ("Smith, John", "John Smith"),
+            ("Wang Wei", "Wei Wang"),
+            ("John Smith", "John Michael Smith"),
+            ("Siobhan OBrien", "Siobhán O'Brien"),
+            ("Nguyen Van Duc", "Nguyễn Văn Đức"),
+            ("Jon Smith", "John Smith"),
+            ("Catherine Smith-Jones", "Catherine Smith Jones"),
+        ];
+
+        for (submitted, on_record) in variants {
+            assert!(
+                matcher.matches(submitted, on_record),
+                "{:?} should match {:?} (similarity {})",
+                submitted,
+                on_record,
+                similarity(submitted, on_record)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_different_people() {
+        let matcher = NameMatcher::new(0.9);
+
+        let different = [
+            ("John Smith", "John Doe"),
+            ("Jane Doe", "John Smith"),
+            ("John", "John Smith"),
+            ("Smith", "John Smith"),
+            ("Michael Brown", "Michelle Green"),
+            // padded with names that aren't on record
+            ("John Michael Smith", "John Smith"),
+            ("John Smith Jane Doe", "John Smith"),
+            ("Jane John Smith Doe Brown", "John Smith"),
+            ("Aaron Bob Chris John Smith", "John Smith"),
+            ("", "John Smith"),
+            ("John Smith", ""),
+        ];
+
+        for (submitted, on_record) in different {
+            assert!(
+                !matcher.matches(submitted, on_record),
+                "{:?} should not match {:?} (similarity {})",
+                submitted,
+                on_record,
+                similarity(submitted, on_record)
+            );
+        }
+    }
+
+    #[test]
+    fn threshold_is_configurable() {
+        let strict = NameMatcher::new(1.0);
+        let loose = NameMatcher::new(0.8);
+
+        assert!(!strict.matches("Jon Smith", "John Smith"));
+        assert!(loose.matches("Jon Smith", "John Smith"));
+        assert!(strict.matches("Smith John", "John Smith"));
+    }
+}
@@ -0,0 +1,282 @@
+use poise::serenity_prelude::{
+    ButtonStyle, Colour, CreateActionRow, CreateButton, CreateEmbed, CreateSelectMenu,
+    CreateSelectMenuKind, CreateSelectMenuOption, Timestamp, UserId,
+};
+use serde::{Deserialize, Serialize};
+use supabase::Client;
+use tracing::instrument;
+
+use crate::verification::roles::RoleMap;
+
+const PROFILES_TABLE: &str = "member_profiles";
+
+/// custom_id of the campus select menu
+pub const CAMPUS_SELECT_ID: &str = "onboarding_campus";
+/// custom_id of the year level select menu
+pub const YEAR_SELECT_ID: &str = "onboarding_year";
+/// custom_id of the button that opens the course modal
+pub const COURSE_BUTTON_ID: &str = "onboarding_course";
+
+/// One of the fixed answers offered in an onboarding select menu
+pub trait OnboardingChoice: Copy + PartialEq + 'static {
+    const ALL: &'static [Self];
+
+    /// value stored in Supabase, used as the select option value and the key in the role mapping
+    fn key(&self) -> &'static str;
+
+    fn label(&self) -> &'static str;
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|choice| choice.key() == key)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Campus {
+    Burwood,
+    WaurnPonds,
+    Warrnambool,
+    Cloud,
+}
+
+impl OnboardingChoice for Campus {
+    const ALL: &'static [Self] = &[
+        Campus::Burwood,
+        Campus::WaurnPonds,
+        Campus::Warrnambool,
+        Campus::Cloud,
+    ];
+
+    fn key(&self) -> &'static str {
+        match self {
+            Campus::Burwood => "burwood",
+            Campus::WaurnPonds => "waurn_ponds",
+            Campus::Warrnambool => "warrnambool",
+            Campus::Cloud => "cloud",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Campus::Burwood => "Burwood",
+            Campus::WaurnPonds => "Waurn Ponds",
+            Campus::Warrnambool => "Warrnambool",
+            Campus::Cloud => "Cloud (online)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum YearLevel {
+    First,
+    Second,
+    Third,
+    FourthPlus,
+    Postgraduate,
+}
+
+impl OnboardingChoice for YearLevel {
+    const ALL: &'static [Self] = &[
+        YearLevel::First,
+        YearLevel::Second,
+        YearLevel::Third,
+        YearLevel::FourthPlus,
+        YearLevel::Postgraduate,
+    ];
+
+    fn key(&self) -> &'static str {
+        match self {
+            YearLevel::First => "first",
+            YearLevel::Second => "second",
+            YearLevel::Third => "third",
+            YearLevel::FourthPlus => "fourth_plus",
+            YearLevel::Postgraduate => "postgraduate",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            YearLevel::First => "1st year",
+            YearLevel::Second => "2nd year",
+            YearLevel::Third => "3rd year",
+            YearLevel::FourthPlus => "4th year or later",
+            YearLevel::Postgraduate => "Postgraduate",
+        }
+    }
+}
+
+/// One row of the `member_profiles` table, the optional details a member gives after verifying
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberProfile {
+    pub discord_user_id: UserId,
+    pub student_id: String,
+    pub course: Option<String>,
+    pub campus: Option<Campus>,
+    pub year_level: Option<YearLevel>,
+    pub updated_at: Timestamp,
+}
+
+impl MemberProfile {
+    pub fn new(discord_user_id: UserId, student_id: impl Into<String>) -> Self {
+        Self {
+            discord_user_id,
+            student_id: student_id.into(),
+            course: None,
+            campus: None,
+            year_level: None,
+            updated_at: Timestamp::now(),
+        }
+    }
+}
+
+/// Settings for the onboarding step
+#[derive(Debug, Clone)]
+pub struct OnboardingConfig {
+    /// ask for course, campus and year level after verifying
+    pub enabled: bool,
+    pub campus_roles: RoleMap,
+    pub year_roles: RoleMap,
+}
+
+fn select_menu<T: OnboardingChoice>(
+    custom_id: &str,
+    placeholder: &str,
+    selected: Option<T>,
+) -> CreateActionRow {
+    let options = T::ALL
+        .iter()
+        .map(|choice| {
+            CreateSelectMenuOption::new(choice.label(), choice.key())
+                .default_selection(Some(*choice) == selected)
+        })
+        .collect();
+
+    CreateActionRow::SelectMenu(
+        CreateSelectMenu::new(custom_id, CreateSelectMenuKind::String { options })
+            .placeholder(placeholder),
+    )
+}
+
+/// Reads and writes `member_profiles`, and builds the onboarding message
+#[derive(Debug)]
+pub struct Onboarding {
+    config: OnboardingConfig,
+    supabase: Client,
+}
+
+impl Onboarding {
+    pub fn new(config: OnboardingConfig, supabase: Client) -> Self {
+        Self { config, supabase }
+    }
+
+    pub fn config(&self) -> &OnboardingConfig {
+        &self.config
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = PROFILES_TABLE, query = "find"))]
+    pub async fn find(&self, user_id: UserId) -> supabase::Result<Option<MemberProfile>> {
+        let profiles: Vec<MemberProfile> = self
+            .supabase
+            .database()
+            .from(PROFILES_TABLE)
+            .select("*")
+            .eq("discord_user_id", &user_id.to_string())
+            .execute()
+            .await?;
+
+        Ok(profiles.into_iter().next())
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = PROFILES_TABLE, query = "save"))]
+    pub async fn save(&self, profile: &MemberProfile) -> supabase::Result<()> {
+        self.supabase
+            .database()
+            .upsert(PROFILES_TABLE)
+            .values(profile)?
+            .returning("*")
+            .execute::<MemberProfile>()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Removes the user's profile, returning whether they had one
+    #[instrument(name = "supabase", skip_all, fields(table = PROFILES_TABLE, query = "delete"))]
+    pub async fn delete(&self, user_id: UserId) -> supabase::Result<bool> {
+        let removed: Vec<MemberProfile> = self
+            .supabase
+            .database()
+            .delete(PROFILES_TABLE)
+            .eq("discord_user_id", &user_id.to_string())
+            .returning("*")
+            .execute()
+            .await?;
+
+        Ok(!removed.is_empty())
+    }
+
+    /// Embed and components asking for the details the member hasn't given yet
+    pub fn message(&self, profile: &MemberProfile) -> (CreateEmbed, Vec<CreateActionRow>) {
+        let answer = |value: Option<&str>| value.unwrap_or("*not set*").to_string();
+
+        let embed = CreateEmbed::new()
+            .title("Tell us a bit about yourself 🎓")
+            .description("Optional: pick your campus and year level, and add your course. This helps the committee plan events and gives you matching roles.")
+            .field("Course", answer(profile.course.as_deref()), true)
+            .field(
+                "Campus",
+                answer(profile.campus.as_ref().map(Campus::label)),
+                true,
+            )
+            .field(
+                "Year level",
+                answer(profile.year_level.as_ref().map(YearLevel::label)),
+                true,
+            )
+            .colour(Colour::BLURPLE);
+
+        let course_button = CreateButton::new(COURSE_BUTTON_ID)
+            .label(if profile.course.is_some() {
+                "Change course"
+            } else {
+                "Add course"
+            })
+            .style(ButtonStyle::Secondary);
+
+        let components = vec![
+            select_menu(CAMPUS_SELECT_ID, "Campus", profile.campus),
+            select_menu(YEAR_SELECT_ID, "Year level", profile.year_level),
+            CreateActionRow::Buttons(vec![course_button]),
+        ];
+
+        (embed, components)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn choice_keys_match_serde_names() {
+        for campus in Campus::ALL {
+            assert_eq!(Campus::from_key(campus.key()), Some(*campus));
+            assert_eq!(
+                serde_json::to_value(campus).unwrap(),
+                serde_json::Value::String(campus.key().to_string())
+            );
+        }
+
+        for year_level in YearLevel::ALL {
+            assert_eq!(YearLevel::from_key(year_level.key()), Some(*year_level));
+            assert_eq!(
+                serde_json::to_value(year_level).unwrap(),
+                serde_json::Value::String(year_level.key().to_string())
+            );
+        }
+
+        assert_eq!(Campus::from_key("geelong"), None);
+    }
+}
@@ -0,0 +1,195 @@
+use poise::serenity_prelude::{
+    ButtonStyle, Colour, CreateActionRow, CreateButton, CreateEmbed, CreateMessage, Permissions,
+    Role, RoleId,
+};
+
+use crate::verification::email;
+
+/// custom_id of the button on a panel that grants the default verified role
+pub const VERIFY_BUTTON_ID: &str = "verify";
+// panels for other roles put the role ID after the prefix, e.g. "verify:123"
+const VERIFY_ROLE_PREFIX: &str = "verify:";
+
+const DEFAULT_TITLE: &str = "Verify your DSEC membership";
+const DEFAULT_DESCRIPTION: &str = "Click **Verify Here** and enter your **Full name** and **Student ID** (e.g., s123456789). Your responses are private.";
+const DEFAULT_BUTTON_LABEL: &str = "Verify Here";
+/// label of the button for verifying by email, on panels and failure messages
+pub const EMAIL_BUTTON_LABEL: &str = "Use Deakin email";
+
+/// custom_id of the verify button for a panel, `None` meaning the default verified role
+pub fn button_id(role_id: Option<RoleId>) -> String {
+    match role_id {
+        Some(role_id) => format!("{}{}", VERIFY_ROLE_PREFIX, role_id),
+        None => VERIFY_BUTTON_ID.to_string(),
+    }
+}
+
+/// Parses a verify button's custom_id, returning the role the panel grants (`None` for the default)
+pub fn parse_button_id(custom_id: &str) -> Option<Option<RoleId>> {
+    if custom_id == VERIFY_BUTTON_ID {
+        return Some(None);
+    }
+
+    let role_id: u64 = custom_id.strip_prefix(VERIFY_ROLE_PREFIX)?.parse().ok()?;
+
+    // RoleId::new panics on zero
+    (role_id != 0).then(|| Some(RoleId::new(role_id)))
+}
+
+// anyone who verifies through a panel gets its role, so it can't carry any of these
+const MODERATION_PERMISSIONS: Permissions = Permissions::ADMINISTRATOR
+    .union(Permissions::BAN_MEMBERS)
+    .union(Permissions::KICK_MEMBERS)
+    .union(Permissions::MODERATE_MEMBERS)
+    .union(Permissions::MANAGE_GUILD)
+    .union(Permissions::MANAGE_ROLES)
+    .union(Permissions::MANAGE_CHANNELS)
+    .union(Permissions::MANAGE_MESSAGES)
+    .union(Permissions::MANAGE_THREADS)
+    .union(Permissions::MANAGE_NICKNAMES)
+    .union(Permissions::MANAGE_WEBHOOKS)
+    .union(Permissions::MANAGE_GUILD_EXPRESSIONS)
+    .union(Permissions::MANAGE_EVENTS)
+    .union(Permissions::MENTION_EVERYONE)
+    .union(Permissions::VIEW_AUDIT_LOG);
+
+/// Why a panel can't grant the role, `None` if it can.
+///
+/// `invoker_position` is the highest role position of the member creating the panel (`None` for
+/// the server owner) and `bot_position` the bot's.
+pub fn panel_role_problem(
+    role: &Role,
+    invoker_position: Option<u16>,
+    bot_position: u16,
+) -> Option<&'static str> {
+    if role.managed {
+        return Some("it's managed by an integration");
+    }
+
+    if role.permissions.intersects(MODERATION_PERMISSIONS) {
+        return Some("it has moderation permissions");
+    }
+
+    if invoker_position.is_some_and(|position| role.position >= position) {
+        return Some("it isn't below your highest role");
+    }
+
+    if role.position >= bot_position {
+        return Some("it isn't below the bot's highest role");
+    }
+
+    None
+}
+
+/// Embed and button posted by `/verify`
+#[derive(Debug, Clone)]
+pub struct VerifyPanel {
+    pub title: String,
+    pub description: String,
+    pub colour: Option<Colour>,
+    pub image_url: Option<String>,
+    pub button_label: String,
+    /// role granted instead of `VERIFIED_ROLE_ID`
+    pub role_id: Option<RoleId>,
+    /// add a second button for verifying with a university email address
+    pub email_button: bool,
+}
+
+impl Default for VerifyPanel {
+    fn default() -> Self {
+        Self {
+            title: DEFAULT_TITLE.to_string(),
+            description: DEFAULT_DESCRIPTION.to_string(),
+            colour: None,
+            image_url: None,
+            button_label: DEFAULT_BUTTON_LABEL.to_string(),
+            role_id: None,
+            email_button: false,
+        }
+    }
+}
+
+impl VerifyPanel {
+    pub fn to_message(&self) -> CreateMessage {
+        let mut embed = CreateEmbed::new()
+            .title(&self.title)
+            .description(&self.description);
+
+        if let Some(colour) = self.colour {
+            embed = embed.colour(colour);
+        }
+
+        if let Some(image_url) = &self.image_url {
+            embed = embed.image(image_url);
+        }
+
+        let mut buttons =
+            vec![CreateButton::new(button_id(self.role_id)).label(&self.button_label)];
+
+        if self.email_button {
+            buttons.push(
+                CreateButton::new(email::button_id(self.role_id))
+                    .label(EMAIL_BUTTON_LABEL)
+                    .style(ButtonStyle::Secondary),
+            );
+        }
+
+        CreateMessage::new()
+            .add_embed(embed)
+            .components(vec![CreateActionRow::Buttons(buttons)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn button_ids_round_trip() {
+        assert_eq!(parse_button_id(&button_id(None)), Some(None));
+        assert_eq!(
+            parse_button_id(&button_id(Some(RoleId::new(42)))),
+            Some(Some(RoleId::new(42)))
+        );
+
+        // the original panels posted before roles could be chosen
+        assert_eq!(parse_button_id("verify"), Some(None));
+
+        assert_eq!(parse_button_id("verify:"), None);
+        assert_eq!(parse_button_id("verify:abc"), None);
+        assert_eq!(parse_button_id("verify:0"), None);
+        assert_eq!(parse_button_id("verify_review_request"), None);
+    }
+
+    fn role(position: u16, permissions: Permissions) -> Role {
+        let mut role = Role::default();
+        role.position = position;
+        role.permissions = permissions;
+        role
+    }
+
+    #[test]
+    fn panel_roles_must_be_harmless_and_below_both() {
+        let plain = role(3, Permissions::SEND_MESSAGES | Permissions::VIEW_CHANNEL);
+        assert_eq!(panel_role_problem(&plain, Some(5), 10), None);
+        assert_eq!(panel_role_problem(&plain, None, 10), None);
+
+        assert!(panel_role_problem(&plain, Some(3), 10).is_some());
+        assert!(panel_role_problem(&plain, Some(2), 10).is_some());
+        assert!(panel_role_problem(&plain, None, 3).is_some());
+
+        for permissions in [
+            Permissions::ADMINISTRATOR,
+            Permissions::BAN_MEMBERS,
+            Permissions::MANAGE_GUILD,
+            Permissions::MANAGE_ROLES,
+            Permissions::SEND_MESSAGES | Permissions::MODERATE_MEMBERS,
+        ] {
+            assert!(panel_role_problem(&role(3, permissions), None, 10).is_some());
+        }
+
+        let mut managed = role(3, Permissions::empty());
+        managed.managed = true;
+        assert!(panel_role_problem(&managed, None, 10).is_some());
+    }
+}
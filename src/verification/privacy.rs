@@ -0,0 +1,287 @@
+use poise::serenity_prelude as serenity;
+use serde::Serialize;
+use serenity::all::{
+    ButtonStyle, Colour, CreateActionRow, CreateAttachment, CreateButton, CreateEmbed,
+    CreateEmbedFooter, GuildId, Timestamp, User, UserId,
+};
+
+use crate::{
+    AppState, Error,
+    commands::verification::StudentRow,
+    verification::{
+        audit::AuditRecord,
+        links::{EmailLink, StudentLink},
+        onboarding::MemberProfile,
+    },
+};
+
+/// custom_id of the button confirming `/privacy delete`
+pub const ERASE_CONFIRM_ID: &str = "privacy_erase_confirm";
+/// custom_id of the button cancelling `/privacy delete`
+pub const ERASE_CANCEL_ID: &str = "privacy_erase_cancel";
+
+/// Everything the bot stores about a Discord user, sent to them as JSON by `/privacy export`
+#[derive(Debug, Clone, Serialize)]
+pub struct DataExport {
+    pub discord_user_id: UserId,
+    pub exported_at: Timestamp,
+    pub link: Option<StudentLink>,
+    pub email_link: Option<EmailLink>,
+    /// the club's membership record for the linked student ID
+    pub membership: Option<StudentRow>,
+    pub profile: Option<MemberProfile>,
+    pub audit: Vec<AuditRecord>,
+}
+
+impl DataExport {
+    pub async fn collect(state: &AppState, user_id: UserId) -> Result<Self, Error> {
+        let link = state.links.find_by_user(user_id).await?;
+
+        let membership = match &link {
+            Some(link) => state.directory.find_member(&link.student_id).await?,
+            None => None,
+        };
+
+        Ok(Self {
+            discord_user_id: user_id,
+            exported_at: Timestamp::now(),
+            link,
+            email_link: state.email_links.find_by_user(user_id).await?,
+            membership,
+            profile: state.onboarding.find(user_id).await?,
+            audit: state.audit.all_for_user(user_id).await?,
+        })
+    }
+
+    pub fn to_attachment(&self) -> Result<CreateAttachment, Error> {
+        let json = serde_json::to_vec_pretty(self)?;
+
+        Ok(CreateAttachment::bytes(
+            json,
+            format!("dsec-verification-{}.json", self.discord_user_id),
+        ))
+    }
+
+    /// Committee notice that the user exported their data
+    pub fn to_embed(&self, user: &User) -> CreateEmbed {
+        CreateEmbed::new()
+            .title("Verification data exported 📦")
+            .field("User", format!("<@{}>", user.id), true)
+            .field(
+                "Linked",
+                if self.link.is_some() { "Yes" } else { "No" },
+                true,
+            )
+            .field("Audit records", self.audit.len().to_string(), true)
+            .colour(Colour::BLURPLE)
+            .footer(privacy_footer(user))
+            .timestamp(self.exported_at)
+    }
+}
+
+/// What `/privacy delete` removed, and which steps failed
+#[derive(Debug, Clone, Default)]
+pub struct Erasure {
+    pub link: Option<StudentLink>,
+    pub email_link: bool,
+    pub audit_records: usize,
+    pub profile: bool,
+    pub cache_entry: bool,
+    pub roles_removed: bool,
+    /// steps that failed, e.g. "your verification log entries"
+    pub failed: Vec<&'static str>,
+}
+
+// messages already posted to the committee aren't touched by the erasure
+const POSTED_MESSAGES_NOTE: &str = "Verification log and review messages the committee received earlier still show your student ID, ask the committee if you'd like them deleted.";
+
+impl Erasure {
+    /// Summary shown to the member
+    pub fn to_reply_embed(&self) -> CreateEmbed {
+        let mut removed = vec![format!("{} verification log entries", self.audit_records)];
+
+        if self.link.is_some() {
+            removed.push("your student ID link".to_string());
+        }
+        if self.email_link {
+            removed.push("your verified email address".to_string());
+        }
+        if self.profile {
+            removed.push("your course, campus and year level".to_string());
+        }
+        if self.cache_entry {
+            removed.push("the cached copy of your membership record".to_string());
+        }
+        if self.roles_removed {
+            removed.push("your verified and membership roles".to_string());
+        }
+
+        let (title, colour) = if self.failed.is_empty() {
+            (
+                "Your verification data has been deleted 🗑️",
+                Colour::DARK_GREEN,
+            )
+        } else {
+            (
+                "Some of your verification data wasn't deleted ⚠️",
+                Colour::ORANGE,
+            )
+        };
+
+        let mut description = format!("Removed {}.", removed.join(", "));
+
+        if !self.failed.is_empty() {
+            description.push_str(&format!(
+                "\n\nCouldn't remove {}. Run `/privacy delete` again, or contact the committee if it keeps failing.",
+                self.failed.join(", ")
+            ));
+        }
+
+        description.push_str(&format!(
+            "\n\n{}\n\nThe club's own membership list isn't stored by the bot, contact the committee to change it. You can verify again at any time.",
+            POSTED_MESSAGES_NOTE
+        ));
+
+        CreateEmbed::new()
+            .title(title)
+            .description(description)
+            .colour(colour)
+    }
+
+    /// Committee notice, without the student ID that was erased
+    pub fn to_embed(&self, user: &User) -> CreateEmbed {
+        let mut embed = CreateEmbed::new()
+            .title("Verification data erased 🗑️")
+            .description(format!(
+                "Earlier mod-log and review messages about <@{}> still contain their student ID.",
+                user.id
+            ))
+            .field("User", format!("<@{}>", user.id), true)
+            .field(
+                "Was linked",
+                if self.link.is_some() { "Yes" } else { "No" },
+                true,
+            )
+            .field("Audit records", self.audit_records.to_string(), true)
+            .colour(Colour::DARK_GREY)
+            .footer(privacy_footer(user))
+            .timestamp(Timestamp::now());
+
+        if !self.failed.is_empty() {
+            embed = embed
+                .field("Failed", self.failed.join("\n"), false)
+                .colour(Colour::ORANGE);
+        }
+
+        embed
+    }
+}
+
+fn privacy_footer(user: &User) -> CreateEmbedFooter {
+    CreateEmbedFooter::new(format!(
+        "{} • ID: {} • requested by the member",
+        user.name, user.id
+    ))
+}
+
+/// Confirm and cancel buttons sent with the `/privacy delete` warning
+pub fn erase_buttons() -> CreateActionRow {
+    CreateActionRow::Buttons(vec![
+        CreateButton::new(ERASE_CONFIRM_ID)
+            .label("Delete my data")
+            .style(ButtonStyle::Danger),
+        CreateButton::new(ERASE_CANCEL_ID)
+            .label("Cancel")
+            .style(ButtonStyle::Secondary),
+    ])
+}
+
+/// Removes everything the bot stores about the user. Roles are only removed when a guild is
+/// given and they're still in it, since they no longer match a verified member.
+///
+/// Every step is attempted even if an earlier one fails, the failures are listed in `failed`.
+pub async fn erase(
+    ctx: &serenity::Context,
+    state: &AppState,
+    guild_id: Option<GuildId>,
+    user_id: UserId,
+) -> Erasure {
+    let mut erasure = Erasure::default();
+
+    let failed = |step: &'static str, err: &dyn std::fmt::Display| {
+        tracing::error!(user = user_id.get(), step, error = %err, "Failed to erase verification data");
+        step
+    };
+
+    match state.links.clear_user(user_id).await {
+        Ok(link) => erasure.link = link,
+        Err(err) => erasure.failed.push(failed("your student ID link", &err)),
+    }
+
+    match state.email_links.clear_user(user_id).await {
+        Ok(link) => erasure.email_link = link.is_some(),
+        Err(err) => erasure
+            .failed
+            .push(failed("your verified email address", &err)),
+    }
+
+    if let Some(link) = &erasure.link {
+        erasure.cache_entry = state.student_cache.invalidate(&link.student_id);
+    }
+
+    match state.audit.delete_for_user(user_id).await {
+        Ok(count) => erasure.audit_records = count,
+        Err(err) => erasure
+            .failed
+            .push(failed("your verification log entries", &err)),
+    }
+
+    match state.onboarding.delete(user_id).await {
+        Ok(deleted) => erasure.profile = deleted,
+        Err(err) => erasure
+            .failed
+            .push(failed("your course, campus and year level", &err)),
+    }
+
+    // in-memory traces of verification attempts
+    state.throttle.record_success(user_id);
+    state.reviews.take(user_id);
+    state.email.cancel(user_id);
+
+    if let Some(guild_id) = guild_id
+        && let Ok(member) = guild_id.member(ctx, user_id).await
+    {
+        let onboarding = state.onboarding.config();
+        let verified_role_id = state.config.verified_role_id;
+
+        let mut results = vec![
+            state.tiers.sync(ctx, &member, None).await,
+            onboarding.campus_roles.sync(ctx, &member, None).await,
+            onboarding.year_roles.sync(ctx, &member, None).await,
+        ];
+
+        if member.roles.contains(&verified_role_id) {
+            results.push(
+                member
+                    .remove_role(ctx, verified_role_id)
+                    .await
+                    .map(|_| true)
+                    .map_err(Error::from),
+            );
+        }
+
+        for result in results {
+            match result {
+                Ok(changed) => erasure.roles_removed |= changed,
+                Err(err) => {
+                    let step = failed("your roles", &err);
+                    if !erasure.failed.contains(&step) {
+                        erasure.failed.push(step);
+                    }
+                }
+            }
+        }
+    }
+
+    erasure
+}
@@ -0,0 +1,296 @@
+use std::{collections::HashSet, net::SocketAddr, sync::Arc};
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    AppState, Error,
+    commands::verification::StudentRow,
+    config::Secret,
+    verification::{import::ImportDiff, student_id::StudentId},
+};
+
+/// Settings for the push sync endpoint
+#[derive(Debug, Clone)]
+pub struct PushConfig {
+    /// address to listen on, the endpoint is off when unset
+    pub addr: Option<SocketAddr>,
+    /// bearer token callers must send
+    pub token: Secret,
+}
+
+/// Body of `POST /members/sync`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PushRequest {
+    /// members to add, or replace the record of
+    #[serde(default)]
+    pub upsert: Vec<StudentRow>,
+    /// student IDs to remove
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+/// A request with its student IDs normalised
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidPush {
+    pub upsert: Vec<StudentRow>,
+    pub remove: Vec<String>,
+}
+
+impl PushRequest {
+    /// Normalises the student IDs, returning every problem with the request at once
+    pub fn validate(self) -> Result<ValidPush, Vec<String>> {
+        let mut errors = Vec::new();
+        let mut seen = HashSet::new();
+        let mut upsert = Vec::new();
+        let mut remove = Vec::new();
+
+        for (index, mut member) in self.upsert.into_iter().enumerate() {
+            match member.student_id.parse::<StudentId>() {
+                Ok(student_id) => member.student_id = student_id.as_str().to_string(),
+                Err(err) => {
+                    errors.push(format!("upsert[{}]: {}", index, err));
+                    continue;
+                }
+            }
+
+            member.full_name = member.full_name.trim().to_string();
+            member.membership_type = member
+                .membership_type
+                .map(|membership_type| membership_type.trim().to_string())
+                .filter(|membership_type| !membership_type.is_empty());
+
+            if member.full_name.is_empty() {
+                errors.push(format!("upsert[{}]: full_name is empty", index));
+            } else if !seen.insert(member.student_id.clone()) {
+                errors.push(format!(
+                    "upsert[{}]: s{} appears more than once",
+                    index, member.student_id
+                ));
+            } else {
+                upsert.push(member);
+            }
+        }
+
+        for (index, student_id) in self.remove.iter().enumerate() {
+            match student_id.parse::<StudentId>() {
+                Ok(student_id) if !seen.insert(student_id.as_str().to_string()) => errors.push(
+                    format!("remove[{}]: {} appears more than once", index, student_id),
+                ),
+                Ok(student_id) => remove.push(student_id.as_str().to_string()),
+                Err(err) => errors.push(format!("remove[{}]: {}", index, err)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(ValidPush { upsert, remove })
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Counts returned to the caller
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PushResponse {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+// compares every byte so the time taken doesn't reveal how much of the token matched
+fn token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (left, right)| diff | (left ^ right))
+            == 0
+}
+
+fn authorised(headers: &HeaderMap, token: &Secret) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| token_matches(token.expose(), given.trim()))
+}
+
+fn error_response(status: StatusCode, errors: Vec<String>) -> Response {
+    (status, Json(json!({ "errors": errors }))).into_response()
+}
+
+/// Applies the push to the member directory and drops the affected cache entries
+pub async fn apply(state: &AppState, push: ValidPush) -> Result<PushResponse, Error> {
+    let upsert_ids: Vec<String> = push
+        .upsert
+        .iter()
+        .map(|member| member.student_id.clone())
+        .collect();
+
+    let current = state.directory.find_members(&upsert_ids).await?;
+    let mut diff = ImportDiff::new(current, push.upsert, false);
+    diff.removed = state.directory.find_members(&push.remove).await?;
+
+    state.directory.upsert_members(&diff.upserts()).await?;
+    state.directory.remove_members(&diff.removals()).await?;
+
+    // also drop cached "not found" answers for IDs that were just added
+    for student_id in diff.affected_ids() {
+        state.student_cache.invalidate(&student_id);
+    }
+
+    if !diff.is_empty() {
+        let mut sync = diff.to_sync_record();
+        sync.source = "push".to_string();
+
+        // the members are already written, the sync record only feeds /membership
+        if let Err(err) = state.directory.record_sync(&sync).await {
+            tracing::error!(error = %err, "Failed to record membership push");
+        }
+    }
+
+    Ok(PushResponse {
+        added: diff.added.len(),
+        updated: diff.updated.len(),
+        removed: diff.removed.len(),
+        unchanged: diff.unchanged,
+    })
+}
+
+async fn sync_members(
+    State((state, config)): State<(Arc<AppState>, Arc<PushConfig>)>,
+    headers: HeaderMap,
+    body: Result<Json<PushRequest>, axum::extract::rejection::JsonRejection>,
+) -> Response {
+    if !authorised(&headers, &config.token) {
+        return error_response(
+            StatusCode::UNAUTHORIZED,
+            vec!["missing or wrong bearer token".to_string()],
+        );
+    }
+
+    let push = match body {
+        Ok(Json(request)) => request.validate(),
+        Err(rejection) => Err(vec![rejection.body_text()]),
+    };
+
+    let push = match push {
+        Ok(push) => push,
+        Err(errors) => return error_response(StatusCode::BAD_REQUEST, errors),
+    };
+
+    match apply(&state, push).await {
+        Ok(response) => Json(response).into_response(),
+        Err(err) => {
+            tracing::error!(error = %err, "Failed to apply membership push");
+
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                vec!["failed to update the member list".to_string()],
+            )
+        }
+    }
+}
+
+pub fn router(state: Arc<AppState>, config: PushConfig) -> Router {
+    Router::new()
+        .route("/members/sync", post(sync_members))
+        .with_state((state, Arc::new(config)))
+}
+
+/// Serves the endpoint until the process exits, doing nothing when no address is configured
+pub async fn serve(state: Arc<AppState>, config: PushConfig) {
+    let Some(addr) = config.addr else {
+        return;
+    };
+
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            tracing::error!(%addr, error = %err, "Failed to bind push sync endpoint");
+            return;
+        }
+    };
+
+    tracing::info!(%addr, "Push sync endpoint listening");
+
+    if let Err(err) = axum::serve(listener, router(state, config)).await {
+        tracing::error!(error = %err, "Push sync endpoint stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(student_id: &str, full_name: &str) -> StudentRow {
+        StudentRow {
+            full_name: full_name.to_string(),
+            student_id: student_id.to_string(),
+            membership_type: None,
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn normalises_student_ids() {
+        let request = PushRequest {
+            upsert: vec![member("s123456789", " John Doe ")],
+            remove: vec!["S987654321".to_string()],
+        };
+
+        assert_eq!(
+            request.validate(),
+            Ok(ValidPush {
+                upsert: vec![member("123456789", "John Doe")],
+                remove: vec!["987654321".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let request = PushRequest {
+            upsert: vec![
+                member("s12345", "John Doe"),
+                member("s123456789", ""),
+                member("s111111111", "Jane Doe"),
+            ],
+            remove: vec!["s111111111".to_string(), "nope".to_string()],
+        };
+
+        let errors = request.validate().unwrap_err();
+
+        assert_eq!(errors.len(), 4);
+        assert!(errors[0].starts_with("upsert[0]"));
+        assert!(errors[1].starts_with("upsert[1]"));
+        assert!(errors[2].starts_with("remove[0]"));
+        assert!(errors[3].starts_with("remove[1]"));
+    }
+
+    #[test]
+    fn checks_bearer_token() {
+        let token: Secret = "secret-token-1234".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        assert!(!authorised(&headers, &token));
+
+        headers.insert(header::AUTHORIZATION, "Bearer wrong".parse().unwrap());
+        assert!(!authorised(&headers, &token));
+
+        headers.insert(
+            header::AUTHORIZATION,
+            "Bearer secret-token-1234".parse().unwrap(),
+        );
+        assert!(authorised(&headers, &token));
+    }
+}
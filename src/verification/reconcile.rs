@@ -0,0 +1,309 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDate;
+use poise::serenity_prelude::{Colour, CreateEmbed, CreateEmbedFooter, UserId};
+
+use crate::{Error, commands::verification::StudentRow};
+
+// members listed on each page of the report
+const PAGE_SIZE: usize = 15;
+
+/// What's wrong with one member's verification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// holds the verified role without a linked student ID
+    NoLink,
+    /// holds the verified role from email verification, no student ID linked yet
+    EmailOnly,
+    /// holds the verified role, but their student ID isn't in the member list
+    NotInList { student_id: String },
+    /// holds the verified role, but their membership has expired
+    Expired {
+        student_id: String,
+        expires_at: NaiveDate,
+    },
+    /// verified in the past and still an active member, but doesn't have the role
+    MissingRole { student_id: String },
+}
+
+impl Finding {
+    /// value of the CSV `issue` column
+    pub fn key(&self) -> &'static str {
+        match self {
+            Finding::NoLink => "no_link",
+            Finding::EmailOnly => "email_only",
+            Finding::NotInList { .. } => "not_in_member_list",
+            Finding::Expired { .. } => "membership_expired",
+            Finding::MissingRole { .. } => "missing_role",
+        }
+    }
+
+    pub fn student_id(&self) -> Option<&str> {
+        match self {
+            Finding::NoLink | Finding::EmailOnly => None,
+            Finding::NotInList { student_id }
+            | Finding::Expired { student_id, .. }
+            | Finding::MissingRole { student_id } => Some(student_id),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Finding::NoLink => "has the role, no linked student ID".to_string(),
+            Finding::EmailOnly => {
+                "has the role from email verification, no student ID yet".to_string()
+            }
+            Finding::NotInList { student_id } => {
+                format!("has the role, s{} isn't in the member list", student_id)
+            }
+            Finding::Expired {
+                student_id,
+                expires_at,
+            } => format!("has the role, s{} expired on {}", student_id, expires_at),
+            Finding::MissingRole { student_id } => {
+                format!("active member s{} without the role", student_id)
+            }
+        }
+    }
+}
+
+/// A guild member as seen by the report
+#[derive(Debug, Clone)]
+pub struct GuildMember {
+    pub user_id: UserId,
+    pub username: String,
+    pub has_role: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReconcileEntry {
+    pub user_id: UserId,
+    pub username: String,
+    pub finding: Finding,
+}
+
+/// Verified role holders and linked members whose role doesn't match the member list
+#[derive(Debug, Clone, Default)]
+pub struct ReconcileReport {
+    pub role_holders: usize,
+    pub entries: Vec<ReconcileEntry>,
+}
+
+impl ReconcileReport {
+    /// Compares guild members against their links (user to student ID) and membership records.
+    /// `email_verified` are the users with a verified email address.
+    pub fn new(
+        members: Vec<GuildMember>,
+        links: &HashMap<UserId, String>,
+        email_verified: &HashSet<UserId>,
+        records: &HashMap<String, StudentRow>,
+    ) -> Self {
+        let mut report = ReconcileReport::default();
+
+        for member in members {
+            let student_id = links.get(&member.user_id);
+            let record = student_id.and_then(|student_id| records.get(student_id));
+
+            if member.has_role {
+                report.role_holders += 1;
+            }
+
+            let finding = match (member.has_role, student_id, record) {
+                (true, None, _) if email_verified.contains(&member.user_id) => Finding::EmailOnly,
+                (true, None, _) => Finding::NoLink,
+                (true, Some(student_id), None) => Finding::NotInList {
+                    student_id: student_id.clone(),
+                },
+                (true, Some(student_id), Some(record)) => match record.expires_at {
+                    Some(expires_at) if !record.is_current() => Finding::Expired {
+                        student_id: student_id.clone(),
+                        expires_at,
+                    },
+                    _ => continue,
+                },
+                (false, Some(student_id), Some(record)) if record.is_current() => {
+                    Finding::MissingRole {
+                        student_id: student_id.clone(),
+                    }
+                }
+                (false, _, _) => continue,
+            };
+
+            report.entries.push(ReconcileEntry {
+                user_id: member.user_id,
+                username: member.username,
+                finding,
+            });
+        }
+
+        report.entries.sort_by(|left, right| {
+            (left.finding.key(), left.user_id).cmp(&(right.finding.key(), right.user_id))
+        });
+
+        report
+    }
+
+    fn count(&self, key: &str) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.finding.key() == key)
+            .count()
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "Checked **{}** verified role holders.\n**{}** without a link, **{}** verified by email only, **{}** not in the member list, **{}** expired, **{}** active members missing the role.",
+            self.role_holders,
+            self.count("no_link"),
+            self.count("email_only"),
+            self.count("not_in_member_list"),
+            self.count("membership_expired"),
+            self.count("missing_role"),
+        )
+    }
+
+    /// One embed per page of findings, always at least one
+    pub fn to_embeds(&self) -> Vec<CreateEmbed> {
+        let pages: Vec<&[ReconcileEntry]> = self.entries.chunks(PAGE_SIZE).collect();
+        let page_count = pages.len();
+
+        if pages.is_empty() {
+            return vec![
+                CreateEmbed::new()
+                    .title("Verification reconciliation")
+                    .description(format!(
+                        "{}\n\nEverything matches the member list ✅",
+                        self.summary()
+                    ))
+                    .colour(Colour::DARK_GREEN),
+            ];
+        }
+
+        pages
+            .into_iter()
+            .enumerate()
+            .map(|(index, entries)| {
+                let lines: Vec<String> = entries
+                    .iter()
+                    .map(|entry| format!("<@{}>: {}", entry.user_id, entry.finding.describe()))
+                    .collect();
+
+                CreateEmbed::new()
+                    .title("Verification reconciliation")
+                    .description(format!("{}\n\n{}", self.summary(), lines.join("\n")))
+                    .colour(Colour::ORANGE)
+                    .footer(CreateEmbedFooter::new(format!(
+                        "Page {}/{}",
+                        index + 1,
+                        page_count
+                    )))
+            })
+            .collect()
+    }
+
+    /// Every finding as CSV, for sorting out in a spreadsheet
+    pub fn to_csv(&self) -> Result<Vec<u8>, Error> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(["issue", "discord_user_id", "username", "student_id"])?;
+
+        for entry in &self.entries {
+            writer.write_record([
+                entry.finding.key(),
+                &entry.user_id.to_string(),
+                &entry.username,
+                &entry
+                    .finding
+                    .student_id()
+                    .map(|student_id| format!("s{}", student_id))
+                    .unwrap_or_default(),
+            ])?;
+        }
+
+        Ok(writer.into_inner()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(user_id: u64, has_role: bool) -> GuildMember {
+        GuildMember {
+            user_id: UserId::new(user_id),
+            username: format!("user{}", user_id),
+            has_role,
+        }
+    }
+
+    fn record(student_id: &str, expires_at: Option<&str>) -> (String, StudentRow) {
+        (
+            student_id.to_string(),
+            StudentRow {
+                full_name: "John Doe".to_string(),
+                student_id: student_id.to_string(),
+                membership_type: None,
+                expires_at: expires_at.map(|date| date.parse().unwrap()),
+            },
+        )
+    }
+
+    #[test]
+    fn finds_mismatched_roles() {
+        let links: HashMap<UserId, String> = [
+            (2, "200000000"),
+            (3, "300000000"),
+            (4, "400000000"),
+            (5, "500000000"),
+            (6, "600000000"),
+        ]
+        .into_iter()
+        .map(|(user_id, student_id)| (UserId::new(user_id), student_id.to_string()))
+        .collect();
+
+        let records: HashMap<String, StudentRow> = [
+            record("300000000", Some("2000-01-01")),
+            record("400000000", None),
+            record("500000000", None),
+            record("600000000", Some("2000-01-01")),
+        ]
+        .into_iter()
+        .collect();
+
+        let email_verified = HashSet::from([UserId::new(8)]);
+
+        let members = vec![
+            member(1, true),  // no link
+            member(2, true),  // not in the list
+            member(3, true),  // expired
+            member(4, true),  // fine
+            member(5, false), // missing the role
+            member(6, false), // expired and without the role, fine
+            member(7, false), // never verified, fine
+            member(8, true),  // verified by email
+        ];
+
+        let report = ReconcileReport::new(members, &links, &email_verified, &records);
+        let findings: Vec<(u64, &str)> = report
+            .entries
+            .iter()
+            .map(|entry| (entry.user_id.get(), entry.finding.key()))
+            .collect();
+
+        assert_eq!(report.role_holders, 5);
+        assert_eq!(
+            findings,
+            vec![
+                (8, "email_only"),
+                (3, "membership_expired"),
+                (5, "missing_role"),
+                (1, "no_link"),
+                (2, "not_in_member_list"),
+            ]
+        );
+
+        let csv = String::from_utf8(report.to_csv().unwrap()).unwrap();
+        assert!(csv.starts_with("issue,discord_user_id,username,student_id\n"));
+        assert!(csv.contains("missing_role,5,user5,s500000000\n"));
+        assert!(csv.contains("no_link,1,user1,\n"));
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude as serenity;
+use serenity::all::{
+    ButtonStyle, ChannelId, Colour, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter,
+    CreateMessage, RoleId, Timestamp, User, UserId,
+};
+
+use crate::{Error, verification::audit::VerificationOutcome};
+
+/// custom_id of the button shown to members after a failed attempt
+pub const REVIEW_REQUEST_ID: &str = "verify_review_request";
+const APPROVE_PREFIX: &str = "review_approve:";
+const REJECT_PREFIX: &str = "review_reject:";
+
+// how long a failed attempt can still be sent for review
+const ATTEMPT_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// A failed attempt the member may submit for manual review
+#[derive(Debug, Clone)]
+pub struct ReviewAttempt {
+    pub student_id: String,
+    pub submitted_name: String,
+    /// why the attempt failed, only ever shown to the committee
+    pub outcome: VerificationOutcome,
+    /// the name on record for the student ID, if it exists
+    pub record_name: Option<String>,
+    /// role of the panel the attempt came from, `None` for the default verified role
+    pub role_id: Option<RoleId>,
+    created: Instant,
+}
+
+impl ReviewAttempt {
+    pub fn new(
+        student_id: impl Into<String>,
+        submitted_name: impl Into<String>,
+        outcome: VerificationOutcome,
+        record_name: Option<String>,
+        role_id: Option<RoleId>,
+    ) -> Self {
+        Self {
+            student_id: student_id.into(),
+            submitted_name: submitted_name.into(),
+            outcome,
+            record_name,
+            role_id,
+            created: Instant::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewDecision {
+    Approve,
+    Reject,
+}
+
+/// What an Approve/Reject button in the review channel applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewButton {
+    pub decision: ReviewDecision,
+    pub user_id: UserId,
+    pub student_id: String,
+    /// `None` for the default verified role
+    pub role_id: Option<RoleId>,
+}
+
+fn decision_id(prefix: &str, user_id: UserId, attempt: &ReviewAttempt) -> String {
+    match attempt.role_id {
+        Some(role_id) => format!("{}{}:{}:{}", prefix, user_id, attempt.student_id, role_id),
+        None => format!("{}{}:{}", prefix, user_id, attempt.student_id),
+    }
+}
+
+/// Parses the custom_id of an Approve/Reject button
+pub fn parse_decision(custom_id: &str) -> Option<ReviewButton> {
+    let (decision, rest) = if let Some(rest) = custom_id.strip_prefix(APPROVE_PREFIX) {
+        (ReviewDecision::Approve, rest)
+    } else if let Some(rest) = custom_id.strip_prefix(REJECT_PREFIX) {
+        (ReviewDecision::Reject, rest)
+    } else {
+        return None;
+    };
+
+    let (user_id, rest) = rest.split_once(':')?;
+    let user_id: u64 = user_id.parse().ok().filter(|user_id| *user_id != 0)?;
+
+    // reviews for the default role have no role segment
+    let (student_id, role_id) = match rest.split_once(':') {
+        Some((student_id, role_id)) => {
+            let role_id: u64 = role_id.parse().ok().filter(|role_id| *role_id != 0)?;
+            (student_id, Some(RoleId::new(role_id)))
+        }
+        None => (rest, None),
+    };
+
+    Some(ReviewButton {
+        decision,
+        user_id: UserId::new(user_id),
+        student_id: student_id.to_string(),
+        role_id,
+    })
+}
+
+/// Holds failed attempts until the member asks for a review, and posts reviews to the committee channel
+#[derive(Debug)]
+pub struct ReviewQueue {
+    channel: Option<ChannelId>,
+    attempts: Mutex<HashMap<UserId, ReviewAttempt>>,
+}
+
+impl ReviewQueue {
+    pub fn new(channel: Option<ChannelId>) -> Self {
+        Self {
+            channel,
+            attempts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reviews are only offered when `REVIEW_CHANNEL_ID` is set
+    pub fn is_enabled(&self) -> bool {
+        self.channel.is_some()
+    }
+
+    /// Keeps the member's latest failed attempt so they can submit it for review
+    pub fn remember(&self, user_id: UserId, attempt: ReviewAttempt) {
+        let mut attempts = self.attempts.lock().expect("Failed to get review queue");
+        attempts.retain(|_, attempt| attempt.created.elapsed() < ATTEMPT_TTL);
+        attempts.insert(user_id, attempt);
+    }
+
+    /// Takes the member's latest failed attempt, if it hasn't expired
+    pub fn take(&self, user_id: UserId) -> Option<ReviewAttempt> {
+        let mut attempts = self.attempts.lock().expect("Failed to get review queue");
+        attempts
+            .remove(&user_id)
+            .filter(|attempt| attempt.created.elapsed() < ATTEMPT_TTL)
+    }
+
+    /// Posts the attempt to the review channel with Approve/Reject buttons
+    pub async fn submit(
+        &self,
+        ctx: &serenity::Context,
+        user: &User,
+        attempt: &ReviewAttempt,
+    ) -> Result<(), Error> {
+        let Some(channel_id) = self.channel else {
+            return Ok(());
+        };
+
+        let on_record = attempt
+            .record_name
+            .clone()
+            .unwrap_or("*student ID not found*".to_string());
+
+        let role = match attempt.role_id {
+            Some(role_id) => format!("<@&{}>", role_id),
+            None => "Verified".to_string(),
+        };
+
+        let mut embed = CreateEmbed::new()
+            .title("Verification review 📝")
+            .field("Member", format!("<@{}>", user.id), true)
+            .field("Role", role, true)
+            .field("Student ID", format!("s{}", attempt.student_id), true)
+            .field("Reason", attempt.outcome.title(), true)
+            .field("Submitted name", &attempt.submitted_name, true)
+            .field("Name on record", on_record, true)
+            .colour(Colour::GOLD)
+            .footer(CreateEmbedFooter::new(format!(
+                "{} • ID: {}",
+                user.name, user.id
+            )))
+            .timestamp(Timestamp::now());
+
+        // approving can't take a student ID away from the account holding it
+        if attempt.outcome == VerificationOutcome::AlreadyLinked {
+            embed = embed.description(
+                "This student ID is linked to another account. Use `/link transfer` if it belongs to this member.",
+            );
+        }
+
+        let approve = CreateButton::new(decision_id(APPROVE_PREFIX, user.id, attempt))
+            .label("Approve")
+            .style(ButtonStyle::Success);
+
+        let reject = CreateButton::new(decision_id(REJECT_PREFIX, user.id, attempt))
+            .label("Reject")
+            .style(ButtonStyle::Danger);
+
+        let message = CreateMessage::new()
+            .embed(embed)
+            .components(vec![CreateActionRow::Buttons(vec![approve, reject])]);
+
+        channel_id.send_message(ctx, message).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decision_ids_round_trip() {
+        let user_id = UserId::new(7);
+
+        for role_id in [None, Some(RoleId::new(42))] {
+            let attempt = ReviewAttempt::new(
+                "123456789",
+                "John Smith",
+                VerificationOutcome::NameMismatch,
+                None,
+                role_id,
+            );
+
+            let button = parse_decision(&decision_id(REJECT_PREFIX, user_id, &attempt));
+
+            assert_eq!(
+                button,
+                Some(ReviewButton {
+                    decision: ReviewDecision::Reject,
+                    user_id,
+                    student_id: "123456789".to_string(),
+                    role_id,
+                })
+            );
+        }
+
+        assert_eq!(parse_decision("review_approve:0:123"), None);
+        assert_eq!(parse_decision("review_approve:7:123:abc"), None);
+        assert_eq!(parse_decision("verify"), None);
+    }
+}
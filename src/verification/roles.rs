@@ -0,0 +1,110 @@
+use std::{collections::HashMap, str::FromStr};
+
+use poise::serenity_prelude as serenity;
+use serenity::all::{Member, RoleId};
+
+use crate::Error;
+
+/// A set of mutually exclusive roles keyed by name, e.g. one role per campus
+#[derive(Debug, Clone, Default)]
+pub struct RoleMap {
+    // keyed by lowercase name
+    roles: HashMap<String, RoleId>,
+}
+
+impl RoleMap {
+    /// Parses a mapping like "member:123,committee:456,alumni:789"
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut roles = HashMap::new();
+
+        for pair in value.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (name, role_id) = pair
+                .split_once(':')
+                .ok_or(format!("\"{}\" should be name:role_id", pair.trim()))?;
+
+            let role_id: u64 = role_id
+                .trim()
+                .parse()
+                .ok()
+                .filter(|role_id| *role_id != 0)
+                .ok_or(format!("\"{}\" is not a role ID", role_id.trim()))?;
+
+            roles.insert(name.trim().to_lowercase(), RoleId::new(role_id));
+        }
+
+        Ok(Self { roles })
+    }
+
+    pub fn get(&self, name: &str) -> Option<RoleId> {
+        self.roles.get(&name.trim().to_lowercase()).copied()
+    }
+
+    fn contains_role(&self, role_id: &RoleId) -> bool {
+        self.roles.values().any(|mapped| mapped == role_id)
+    }
+
+    /// Roles the member should gain and lose so that `wanted` is the only one of these roles they hold
+    pub fn changes(
+        &self,
+        member: &Member,
+        wanted: Option<RoleId>,
+    ) -> (Option<RoleId>, Vec<RoleId>) {
+        let add = wanted.filter(|role_id| !member.roles.contains(role_id));
+        let remove = member
+            .roles
+            .iter()
+            .filter(|role_id| self.contains_role(role_id) && Some(**role_id) != wanted)
+            .copied()
+            .collect();
+
+        (add, remove)
+    }
+
+    /// Gives the member `wanted` and removes the other roles in the map, returning whether
+    /// anything changed. `None` removes all of them.
+    pub async fn sync(
+        &self,
+        ctx: &serenity::Context,
+        member: &Member,
+        wanted: Option<RoleId>,
+    ) -> Result<bool, Error> {
+        let (add, remove) = self.changes(member, wanted);
+
+        for role_id in &remove {
+            member.remove_role(ctx, role_id).await?;
+        }
+
+        if let Some(role_id) = add {
+            member.add_role(ctx, role_id).await?;
+        }
+
+        Ok(add.is_some() || !remove.is_empty())
+    }
+}
+
+impl FromStr for RoleMap {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::parse(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_name_to_role_pairs() {
+        let roles = RoleMap::parse(" Member:1, committee:2 ,alumni:3,").unwrap();
+
+        assert_eq!(roles.get("member"), Some(RoleId::new(1)));
+        assert_eq!(roles.get("Committee "), Some(RoleId::new(2)));
+        assert_eq!(roles.get("life"), None);
+
+        assert!(RoleMap::parse("").unwrap().roles.is_empty());
+        assert!(RoleMap::parse("member").is_err());
+        assert!(RoleMap::parse("member:abc").is_err());
+        assert!(RoleMap::parse("member:0").is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use std::{fmt, str::FromStr};
+
+// Deakin student IDs are nine digits, usually written with a leading "s"
+const STUDENT_ID_DIGITS: usize = 9;
+
+/// Why a student ID was rejected, displayed to the member as-is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StudentIdError {
+    Empty,
+    InvalidCharacters,
+    WrongLength(usize),
+}
+
+impl fmt::Display for StudentIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StudentIdError::Empty => write!(f, "Enter your student ID, e.g. s123456789."),
+            StudentIdError::InvalidCharacters => write!(
+                f,
+                "A student ID is an \"s\" followed by digits only, e.g. s123456789."
+            ),
+            StudentIdError::WrongLength(digits) => write!(
+                f,
+                "A student ID has {} digits after the \"s\", but that one has {}.",
+                STUDENT_ID_DIGITS, digits
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StudentIdError {}
+
+/// A validated Deakin student ID, stored as its digits only.
+///
+/// `as_str` gives the form used as the cache key and in the database ("123456789"),
+/// `Display` the form shown to people ("s123456789").
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StudentId(String);
+
+impl StudentId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Only the last three digits, for showing someone which ID they're linked to ("s******789")
+    pub fn masked(&self) -> String {
+        let hidden = self.0.len().saturating_sub(3);
+        format!("s{}{}", "*".repeat(hidden), &self.0[hidden..])
+    }
+}
+
+impl FromStr for StudentId {
+    type Err = StudentIdError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let digits = input.strip_prefix(['s', 'S']).unwrap_or(input);
+
+        if digits.is_empty() {
+            return Err(StudentIdError::Empty);
+        }
+
+        if !digits.chars().all(|digit| digit.is_ascii_digit()) {
+            return Err(StudentIdError::InvalidCharacters);
+        }
+
+        if digits.len() != STUDENT_ID_DIGITS {
+            return Err(StudentIdError::WrongLength(digits.len()));
+        }
+
+        Ok(Self(digits.to_string()))
+    }
+}
+
+impl fmt::Display for StudentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_common_spellings() {
+        for input in ["s123456789", "S123456789", "123456789", "  s123456789 "] {
+            let student_id: StudentId = input.parse().unwrap();
+
+            assert_eq!(student_id.as_str(), "123456789");
+            assert_eq!(student_id.to_string(), "s123456789");
+            assert_eq!(student_id.masked(), "s******789");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_ids() {
+        assert_eq!("".parse::<StudentId>(), Err(StudentIdError::Empty));
+        assert_eq!(" s ".parse::<StudentId>(), Err(StudentIdError::Empty));
+        assert_eq!(
+            "s12345678a".parse::<StudentId>(),
+            Err(StudentIdError::InvalidCharacters)
+        );
+        assert_eq!(
+            "ss123456789".parse::<StudentId>(),
+            Err(StudentIdError::InvalidCharacters)
+        );
+        assert_eq!(
+            "s123 456 789".parse::<StudentId>(),
+            Err(StudentIdError::InvalidCharacters)
+        );
+        assert_eq!(
+            "john smith".parse::<StudentId>(),
+            Err(StudentIdError::InvalidCharacters)
+        );
+        assert_eq!(
+            "s12345".parse::<StudentId>(),
+            Err(StudentIdError::WrongLength(5))
+        );
+        assert_eq!(
+            "s1234567890".parse::<StudentId>(),
+            Err(StudentIdError::WrongLength(10))
+        );
+    }
+}
@@ -0,0 +1,662 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use serenity::all::{
+    Colour, CreateEmbed, CreateMessage, GuildId, Member, RoleId, Timestamp, UserId,
+};
+use supabase::Client;
+use tokio::sync::Mutex;
+use tracing::instrument;
+
+use crate::{
+    AppState, Error,
+    commands::verification::StudentRow,
+    verification::audit::{AuditRecord, VerificationOutcome, VerificationPath},
+};
+
+// most members shown per list in the report embed
+const REPORT_LIST_LIMIT: usize = 20;
+
+/// Settings for the membership expiry sweeper
+#[derive(Debug, Clone)]
+pub struct SweepConfig {
+    pub enabled: bool,
+    pub guild_id: Option<GuildId>,
+    pub interval: Duration,
+    /// report what would change without removing roles or sending DMs
+    pub dry_run: bool,
+    /// how long a member can be missing from `active_members` before losing the role
+    pub grace: Duration,
+    /// DM members when their grace period starts
+    pub notify: bool,
+    pub renewal_url: Option<String>,
+}
+
+const GRACE_TABLE: &str = "sweep_grace";
+
+/// When a verified member was first found missing from `active_members`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct GraceState {
+    discord_user_id: UserId,
+    missing_since: Timestamp,
+    /// whether they were sent the grace period DM
+    notified: bool,
+}
+
+/// Where a member ends up in the sweep report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bucket {
+    Active,
+    GraceStarted,
+    InGrace,
+    Removed,
+}
+
+/// What a sweep does for one linked member
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Decision {
+    bucket: Bucket,
+    /// send the grace period DM
+    notify: bool,
+    /// grace state to store
+    save: Option<GraceState>,
+    /// delete the stored grace state
+    forget: bool,
+    /// take away the verified and tier roles
+    remove_roles: bool,
+}
+
+impl Decision {
+    fn report(bucket: Bucket) -> Self {
+        Self {
+            bucket,
+            notify: false,
+            save: None,
+            forget: false,
+            remove_roles: false,
+        }
+    }
+}
+
+/// Works out what happens to a linked member, dry runs only ever report
+fn decide(
+    user_id: UserId,
+    is_active: bool,
+    previous: Option<GraceState>,
+    now: Timestamp,
+    config: &SweepConfig,
+    dry_run: bool,
+) -> Decision {
+    let changes = !dry_run;
+
+    if is_active {
+        return Decision {
+            forget: changes && previous.is_some(),
+            ..Decision::report(Bucket::Active)
+        };
+    }
+
+    // members must be warned before the role goes, so a missed DM restarts the grace period
+    let restart = previous.is_none_or(|previous| config.notify && !previous.notified);
+
+    if restart {
+        return Decision {
+            notify: changes && config.notify,
+            save: changes.then_some(GraceState {
+                discord_user_id: user_id,
+                missing_since: now,
+                notified: config.notify,
+            }),
+            ..Decision::report(Bucket::GraceStarted)
+        };
+    }
+
+    let since = previous.map_or(now, |previous| previous.missing_since);
+    let elapsed = now.unix_timestamp().saturating_sub(since.unix_timestamp());
+
+    if (elapsed as u64) < config.grace.as_secs() {
+        return Decision::report(Bucket::InGrace);
+    }
+
+    Decision {
+        forget: changes,
+        remove_roles: changes,
+        ..Decision::report(Bucket::Removed)
+    }
+}
+
+/// What a sweep found, and what it did (or would do in dry-run mode)
+#[derive(Debug, Default)]
+pub struct SweepReport {
+    pub dry_run: bool,
+    pub checked: usize,
+    pub active: usize,
+    /// verified by email rather than a student ID, sorted into the grace period lists
+    pub email_verified: usize,
+    /// verified members with no linked student ID or email address, left alone
+    pub unlinked: Vec<UserId>,
+    pub grace_started: Vec<UserId>,
+    pub in_grace: Vec<UserId>,
+    pub removed: Vec<UserId>,
+    /// active members whose tier role didn't match their membership type
+    pub tier_updated: Vec<UserId>,
+    /// members the sweep couldn't update, the errors are in the logs
+    pub failed: Vec<UserId>,
+}
+
+fn mention_list(user_ids: &[UserId]) -> String {
+    if user_ids.is_empty() {
+        return "None".to_string();
+    }
+
+    let mut mentions: Vec<String> = user_ids
+        .iter()
+        .take(REPORT_LIST_LIMIT)
+        .map(|user_id| format!("<@{}>", user_id))
+        .collect();
+
+    if user_ids.len() > REPORT_LIST_LIMIT {
+        mentions.push(format!("…and {} more", user_ids.len() - REPORT_LIST_LIMIT));
+    }
+
+    mentions.join(" ")
+}
+
+impl SweepReport {
+    pub fn to_embed(&self) -> CreateEmbed {
+        let title = if self.dry_run {
+            "Membership sweep (dry run) 🧹"
+        } else {
+            "Membership sweep 🧹"
+        };
+
+        let (removed_label, tier_label) = if self.dry_run {
+            ("Would be removed", "Tier role would change")
+        } else {
+            ("Removed", "Tier role changed")
+        };
+
+        CreateEmbed::new()
+            .title(title)
+            .description(format!(
+                "Checked **{}** verified members, **{}** still active, **{}** verified by email.",
+                self.checked, self.active, self.email_verified
+            ))
+            .field(
+                format!("{} ({})", removed_label, self.removed.len()),
+                mention_list(&self.removed),
+                false,
+            )
+            .field(
+                format!("Grace period started ({})", self.grace_started.len()),
+                mention_list(&self.grace_started),
+                false,
+            )
+            .field(
+                format!("In grace period ({})", self.in_grace.len()),
+                mention_list(&self.in_grace),
+                false,
+            )
+            .field(
+                format!("{} ({})", tier_label, self.tier_updated.len()),
+                mention_list(&self.tier_updated),
+                false,
+            )
+            .field(
+                format!("No linked student ID or email ({})", self.unlinked.len()),
+                mention_list(&self.unlinked),
+                false,
+            )
+            .field(
+                format!("Failed, see logs ({})", self.failed.len()),
+                mention_list(&self.failed),
+                false,
+            )
+            .colour(Colour::DARK_GREY)
+            .timestamp(Timestamp::now())
+    }
+}
+
+/// Every guild member, fetched page by page
+pub async fn guild_members(
+    ctx: &serenity::Context,
+    guild_id: GuildId,
+) -> Result<Vec<Member>, Error> {
+    let mut members = Vec::new();
+    let mut after: Option<UserId> = None;
+
+    loop {
+        let page = guild_id.members(ctx, Some(1000), after).await?;
+
+        let Some(last) = page.last() else {
+            break;
+        };
+        after = Some(last.user.id);
+
+        let page_len = page.len();
+        members.extend(page);
+
+        if page_len < 1000 {
+            break;
+        }
+    }
+
+    Ok(members)
+}
+
+/// All guild members holding the verified role
+pub async fn verified_members(
+    ctx: &serenity::Context,
+    guild_id: GuildId,
+    verified_role_id: RoleId,
+) -> Result<Vec<Member>, Error> {
+    Ok(guild_members(ctx, guild_id)
+        .await?
+        .into_iter()
+        .filter(|member| member.roles.contains(&verified_role_id))
+        .collect())
+}
+
+/// Removes the verified role from members who have dropped out of `active_members`
+#[derive(Debug)]
+pub struct Sweeper {
+    config: SweepConfig,
+    // grace periods are kept in `sweep_grace`, so restarts don't reset them
+    supabase: Client,
+    // held for a whole run, so /sweep and the periodic sweep don't overlap
+    running: Mutex<()>,
+}
+
+impl Sweeper {
+    pub fn new(config: SweepConfig, supabase: Client) -> Self {
+        Self {
+            config,
+            supabase,
+            running: Mutex::new(()),
+        }
+    }
+
+    pub fn config(&self) -> &SweepConfig {
+        &self.config
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = GRACE_TABLE, query = "grace_periods"))]
+    async fn grace_periods(&self) -> supabase::Result<HashMap<UserId, GraceState>> {
+        let periods: Vec<GraceState> = self
+            .supabase
+            .database()
+            .from(GRACE_TABLE)
+            .select("*")
+            .execute()
+            .await?;
+
+        Ok(periods
+            .into_iter()
+            .map(|period| (period.discord_user_id, period))
+            .collect())
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = GRACE_TABLE, query = "save_grace"))]
+    async fn save_grace(&self, period: &GraceState) -> supabase::Result<()> {
+        self.supabase
+            .database()
+            .upsert(GRACE_TABLE)
+            .values(period)?
+            .returning("*")
+            .execute::<GraceState>()
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(name = "supabase", skip_all, fields(table = GRACE_TABLE, query = "forget_grace"))]
+    async fn forget_grace(&self, user_id: UserId) -> supabase::Result<()> {
+        self.supabase
+            .database()
+            .delete(GRACE_TABLE)
+            .eq("discord_user_id", &user_id.to_string())
+            .returning("*")
+            .execute::<GraceState>()
+            .await?;
+
+        Ok(())
+    }
+
+    async fn notify(&self, ctx: &serenity::Context, member: &Member, remove_at_unix: i64) {
+        let mut description = format!(
+            "We couldn't find your DSEC membership in our active member list, so your verified role will be removed <t:{}:R>.",
+            remove_at_unix
+        );
+
+        if let Some(url) = &self.config.renewal_url {
+            description.push_str(&format!("\n\nRenew your membership here: {}", url));
+        }
+
+        let embed = CreateEmbed::new()
+            .title("Your DSEC membership has expired")
+            .description(description)
+            .colour(Colour::ORANGE);
+
+        if let Err(err) = member
+            .user
+            .direct_message(ctx, CreateMessage::new().embed(embed))
+            .await
+        {
+            tracing::warn!(
+                user = member.user.id.get(),
+                error = %err,
+                "Failed to DM member about their membership"
+            );
+        }
+    }
+
+    /// Reconciles verified role holders against `active_members`, including their tier roles
+    pub async fn run(
+        &self,
+        ctx: &serenity::Context,
+        state: &AppState,
+        guild_id: GuildId,
+        dry_run: bool,
+    ) -> Result<SweepReport, Error> {
+        let _running = self.running.lock().await;
+
+        let verified_role_id = state.config.verified_role_id;
+        let holders = verified_members(ctx, guild_id, verified_role_id).await?;
+
+        let user_ids: Vec<UserId> = holders.iter().map(|member| member.user.id).collect();
+        let student_ids: HashMap<UserId, String> = state
+            .links
+            .find_by_users(&user_ids)
+            .await?
+            .into_iter()
+            .map(|link| (link.discord_user_id, link.student_id))
+            .collect();
+
+        let email_verified: HashSet<UserId> = state
+            .email_links
+            .find_by_users(&user_ids)
+            .await?
+            .into_iter()
+            .map(|link| link.discord_user_id)
+            .collect();
+
+        let linked_ids: Vec<String> = student_ids.values().cloned().collect();
+        // expired memberships are treated like missing ones
+        let active: HashMap<String, StudentRow> = state
+            .directory
+            .find_members(&linked_ids)
+            .await?
+            .into_iter()
+            .filter(StudentRow::is_current)
+            .map(|member| (member.student_id.clone(), member))
+            .collect();
+
+        let mut grace_periods = self.grace_periods().await?;
+
+        let mut report = SweepReport {
+            dry_run,
+            checked: holders.len(),
+            ..Default::default()
+        };
+
+        let now = Timestamp::now();
+
+        for member in holders {
+            let user_id = member.user.id;
+            let previous = grace_periods.remove(&user_id);
+
+            let student_id = match student_ids.get(&user_id) {
+                Some(student_id) => Some(student_id.as_str()),
+                // not in active_members until they verify with their student ID, so they get a grace period
+                None if email_verified.contains(&user_id) => {
+                    report.email_verified += 1;
+                    None
+                }
+                None => {
+                    report.unlinked.push(user_id);
+                    continue;
+                }
+            };
+
+            let record = student_id.and_then(|student_id| active.get(student_id));
+            let decision = decide(
+                user_id,
+                record.is_some(),
+                previous,
+                now,
+                &self.config,
+                dry_run,
+            );
+
+            // one member failing, e.g. over role hierarchy, shouldn't stop the rest of the sweep
+            match self
+                .apply(ctx, state, &member, record, student_id, &decision, dry_run)
+                .await
+            {
+                Ok(tier_changed) => {
+                    match decision.bucket {
+                        Bucket::Active => report.active += 1,
+                        Bucket::GraceStarted => report.grace_started.push(user_id),
+                        Bucket::InGrace => report.in_grace.push(user_id),
+                        Bucket::Removed => report.removed.push(user_id),
+                    }
+
+                    if tier_changed {
+                        report.tier_updated.push(user_id);
+                    }
+                }
+                Err(err) => {
+                    tracing::error!(user = user_id.get(), error = %err, "Failed to sweep member");
+                    report.failed.push(user_id);
+                }
+            }
+        }
+
+        // whoever is left no longer holds the verified role
+        if !dry_run {
+            for user_id in grace_periods.into_keys() {
+                if let Err(err) = self.forget_grace(user_id).await {
+                    tracing::error!(user = user_id.get(), error = %err, "Failed to forget grace period");
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Carries out `decision` for one member, returning whether their tier roles changed
+    #[allow(clippy::too_many_arguments)]
+    async fn apply(
+        &self,
+        ctx: &serenity::Context,
+        state: &AppState,
+        member: &Member,
+        record: Option<&StudentRow>,
+        student_id: Option<&str>,
+        decision: &Decision,
+        dry_run: bool,
+    ) -> Result<bool, Error> {
+        // saved before the DM, so a failed save doesn't lead to the same DM every sweep
+        if let Some(period) = &decision.save {
+            self.save_grace(period).await?;
+        }
+
+        if decision.notify {
+            let remove_at = Timestamp::now().unix_timestamp() + self.config.grace.as_secs() as i64;
+            self.notify(ctx, member, remove_at).await;
+        }
+
+        if decision.remove_roles {
+            member
+                .remove_role(ctx, state.config.verified_role_id)
+                .await?;
+
+            // the verified role is already gone, a stale tier role shouldn't hide that
+            if let Err(err) = state.tiers.sync(ctx, member, None).await {
+                tracing::error!(user = member.user.id.get(), error = %err, "Failed to remove tier roles");
+            }
+
+            state
+                .audit
+                .record(
+                    ctx,
+                    AuditRecord::new(
+                        &member.user,
+                        student_id.unwrap_or_default(),
+                        VerificationOutcome::MembershipExpired,
+                        VerificationPath::Automatic,
+                    ),
+                )
+                .await;
+        }
+
+        if decision.forget {
+            self.forget_grace(member.user.id).await?;
+        }
+
+        if decision.bucket != Bucket::Active {
+            return Ok(false);
+        }
+
+        if dry_run {
+            let (add, remove) = state.tiers.changes(member, record);
+            Ok(add.is_some() || !remove.is_empty())
+        } else {
+            state.tiers.sync(ctx, member, record).await
+        }
+    }
+}
+
+/// Runs the sweeper every `SWEEP_INTERVAL_HOURS` and posts each report to the mod-log channel
+pub async fn run_periodically(ctx: serenity::Context, state: Arc<AppState>) {
+    let config = state.sweeper.config().clone();
+
+    let Some(guild_id) = config.guild_id else {
+        tracing::warn!(
+            "SWEEP_ENABLED is set but GUILD_ID is missing, membership sweeper not started"
+        );
+        return;
+    };
+
+    let mut interval = tokio::time::interval(config.interval);
+
+    loop {
+        interval.tick().await;
+
+        match state
+            .sweeper
+            .run(&ctx, &state, guild_id, config.dry_run)
+            .await
+        {
+            Ok(report) => state.audit.notify(&ctx, report.to_embed()).await,
+            Err(err) => tracing::error!(error = %err, "Membership sweep failed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: UserId = UserId::new(1);
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn config(notify: bool) -> SweepConfig {
+        SweepConfig {
+            enabled: true,
+            guild_id: None,
+            interval: Duration::from_secs(DAY as u64),
+            dry_run: false,
+            grace: Duration::from_secs(7 * DAY as u64),
+            notify,
+            renewal_url: None,
+        }
+    }
+
+    fn at(days: i64) -> Timestamp {
+        Timestamp::from_unix_timestamp(1_700_000_000 + days * DAY).unwrap()
+    }
+
+    fn grace(since: i64, notified: bool) -> GraceState {
+        GraceState {
+            discord_user_id: USER,
+            missing_since: at(since),
+            notified,
+        }
+    }
+
+    #[test]
+    fn starts_waits_and_removes() {
+        let config = config(true);
+
+        let started = decide(USER, false, None, at(0), &config, false);
+        assert_eq!(started.bucket, Bucket::GraceStarted);
+        assert!(started.notify);
+        assert_eq!(started.save, Some(grace(0, true)));
+
+        let waiting = decide(USER, false, Some(grace(0, true)), at(6), &config, false);
+        assert_eq!(waiting, Decision::report(Bucket::InGrace));
+
+        let removed = decide(USER, false, Some(grace(0, true)), at(7), &config, false);
+        assert_eq!(removed.bucket, Bucket::Removed);
+        assert!(removed.remove_roles && removed.forget);
+    }
+
+    #[test]
+    fn missed_dm_restarts_the_grace_period() {
+        // grace started while notifications were off
+        let restarted = decide(
+            USER,
+            false,
+            Some(grace(0, false)),
+            at(10),
+            &config(true),
+            false,
+        );
+        assert_eq!(restarted.bucket, Bucket::GraceStarted);
+        assert_eq!(restarted.save, Some(grace(10, true)));
+
+        let removed = decide(
+            USER,
+            false,
+            Some(grace(0, false)),
+            at(10),
+            &config(false),
+            false,
+        );
+        assert_eq!(removed.bucket, Bucket::Removed);
+    }
+
+    #[test]
+    fn active_members_forget_their_grace_period() {
+        let config = config(true);
+
+        let active = decide(USER, true, Some(grace(0, true)), at(3), &config, false);
+        assert_eq!(active.bucket, Bucket::Active);
+        assert!(active.forget);
+
+        let active = decide(USER, true, None, at(3), &config, false);
+        assert_eq!(active, Decision::report(Bucket::Active));
+    }
+
+    #[test]
+    fn dry_runs_only_report() {
+        let config = config(true);
+
+        for (is_active, previous, bucket) in [
+            (true, Some(grace(0, true)), Bucket::Active),
+            (false, None, Bucket::GraceStarted),
+            (false, Some(grace(0, false)), Bucket::GraceStarted),
+            (false, Some(grace(0, true)), Bucket::InGrace),
+            (false, Some(grace(-30, true)), Bucket::Removed),
+        ] {
+            let decision = decide(USER, is_active, previous, at(0), &config, true);
+            assert_eq!(decision, Decision::report(bucket));
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude::{GuildId, UserId};
+
+/// Limits for failed verification attempts
+#[derive(Debug, Clone)]
+pub struct ThrottleConfig {
+    /// failed attempts a single user may make inside `user_window` before being locked out
+    pub user_max_failures: usize,
+    pub user_window: Duration,
+    /// length of the first lockout, doubled for every lockout after that
+    pub lockout: Duration,
+    pub max_lockout: Duration,
+    /// failed attempts across the whole server inside `guild_window` before verification pauses
+    pub guild_max_failures: usize,
+    pub guild_window: Duration,
+}
+
+/// Why an attempt was refused, and how long until the user may try again
+#[derive(Debug, Clone, Copy)]
+pub enum Throttled {
+    User { retry_after: Duration },
+    Guild { retry_after: Duration },
+}
+
+impl Throttled {
+    pub fn retry_after(&self) -> Duration {
+        match self {
+            Throttled::User { retry_after } | Throttled::Guild { retry_after } => *retry_after,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct UserRecord {
+    failures: VecDeque<Instant>,
+    // number of lockouts so far, used to escalate the next one
+    strikes: u32,
+    locked_until: Option<Instant>,
+}
+
+impl UserRecord {
+    /// Drops failures outside the window, and lockouts once the user has behaved for a full max
+    /// lockout period
+    fn prune(&mut self, config: &ThrottleConfig, now: Instant) {
+        prune(&mut self.failures, config.user_window, now);
+
+        if let Some(until) = self.locked_until
+            && until <= now
+            && now.duration_since(until) >= config.max_lockout
+        {
+            self.strikes = 0;
+            self.locked_until = None;
+        }
+    }
+
+    // strikes only count while a lockout is remembered, so this is safe to drop
+    fn is_empty(&self) -> bool {
+        self.failures.is_empty() && self.locked_until.is_none()
+    }
+}
+
+/// Tracks failed verification attempts per user and per server
+#[derive(Debug)]
+pub struct VerifyThrottle {
+    config: ThrottleConfig,
+    users: Mutex<HashMap<UserId, UserRecord>>,
+    guilds: Mutex<HashMap<GuildId, VecDeque<Instant>>>,
+}
+
+fn prune(attempts: &mut VecDeque<Instant>, window: Duration, now: Instant) {
+    while let Some(oldest) = attempts.front() {
+        if now.duration_since(*oldest) < window {
+            break;
+        }
+        attempts.pop_front();
+    }
+}
+
+impl VerifyThrottle {
+    pub fn new(config: ThrottleConfig) -> Self {
+        Self {
+            config,
+            users: Mutex::new(HashMap::new()),
+            guilds: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns `Err` if the user (or the whole server) is currently locked out
+    pub fn check(&self, guild_id: GuildId, user_id: UserId) -> Result<(), Throttled> {
+        self.check_at(guild_id, user_id, Instant::now())
+    }
+
+    fn check_at(&self, guild_id: GuildId, user_id: UserId, now: Instant) -> Result<(), Throttled> {
+        {
+            let mut users = self.users.lock().expect("Failed to get throttle");
+            if let Some(record) = users.get_mut(&user_id) {
+                if let Some(until) = record.locked_until
+                    && until > now
+                {
+                    return Err(Throttled::User {
+                        retry_after: until - now,
+                    });
+                }
+
+                record.prune(&self.config, now);
+                if record.is_empty() {
+                    users.remove(&user_id);
+                }
+            }
+        }
+
+        let mut guilds = self.guilds.lock().expect("Failed to get throttle");
+        if let Some(attempts) = guilds.get_mut(&guild_id) {
+            prune(attempts, self.config.guild_window, now);
+
+            if attempts.len() >= self.config.guild_max_failures
+                && let Some(oldest) = attempts.front()
+            {
+                return Err(Throttled::Guild {
+                    retry_after: self.config.guild_window - now.duration_since(*oldest),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Counts a failed attempt, locking the user out once they hit the limit
+    pub fn record_failure(&self, guild_id: GuildId, user_id: UserId) {
+        self.record_failure_at(guild_id, user_id, Instant::now());
+    }
+
+    fn record_failure_at(&self, guild_id: GuildId, user_id: UserId, now: Instant) {
+        {
+            let mut guilds = self.guilds.lock().expect("Failed to get throttle");
+            let attempts = guilds.entry(guild_id).or_default();
+            prune(attempts, self.config.guild_window, now);
+            attempts.push_back(now);
+        }
+
+        let mut users = self.users.lock().expect("Failed to get throttle");
+
+        // users who never come back are only cleaned up here
+        users.retain(|_, record| {
+            record.prune(&self.config, now);
+            !record.is_empty()
+        });
+
+        let record = users.entry(user_id).or_default();
+        record.failures.push_back(now);
+
+        if record.failures.len() >= self.config.user_max_failures {
+            let multiplier = 2u32.saturating_pow(record.strikes);
+            let lockout = self
+                .config
+                .lockout
+                .saturating_mul(multiplier)
+                .min(self.config.max_lockout);
+
+            record.strikes += 1;
+            record.locked_until = Some(now + lockout);
+            record.failures.clear();
+        }
+    }
+
+    /// Clears the user's failure history after they verify successfully
+    pub fn record_success(&self, user_id: UserId) {
+        let mut users = self.users.lock().expect("Failed to get throttle");
+        users.remove(&user_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD: GuildId = GuildId::new(1);
+    const ALICE: UserId = UserId::new(10);
+    const BOB: UserId = UserId::new(11);
+
+    fn throttle() -> VerifyThrottle {
+        VerifyThrottle::new(ThrottleConfig {
+            user_max_failures: 3,
+            user_window: Duration::from_secs(60),
+            lockout: Duration::from_secs(100),
+            max_lockout: Duration::from_secs(300),
+            guild_max_failures: 5,
+            guild_window: Duration::from_secs(60),
+        })
+    }
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn forgets_failures_outside_the_window() {
+        let throttle = throttle();
+        let start = Instant::now();
+
+        throttle.record_failure_at(GUILD, ALICE, start);
+        throttle.record_failure_at(GUILD, ALICE, start + secs(10));
+        // the first failure has left the window by now
+        throttle.record_failure_at(GUILD, ALICE, start + secs(61));
+        assert!(throttle.check_at(GUILD, ALICE, start + secs(61)).is_ok());
+
+        throttle.record_failure_at(GUILD, ALICE, start + secs(62));
+        let Err(Throttled::User { retry_after }) =
+            throttle.check_at(GUILD, ALICE, start + secs(62))
+        else {
+            panic!("expected a user lockout");
+        };
+        assert_eq!(retry_after, secs(100));
+        assert!(throttle.check_at(GUILD, ALICE, start + secs(162)).is_ok());
+    }
+
+    #[test]
+    fn escalates_lockouts_up_to_the_maximum() {
+        let throttle = throttle();
+        let mut now = Instant::now();
+        let mut lockouts = Vec::new();
+
+        for _ in 0..4 {
+            for _ in 0..3 {
+                throttle.record_failure_at(GUILD, ALICE, now);
+            }
+
+            let retry_after = throttle
+                .check_at(GUILD, ALICE, now)
+                .unwrap_err()
+                .retry_after();
+            lockouts.push(retry_after);
+            now += retry_after;
+        }
+
+        assert_eq!(lockouts, [secs(100), secs(200), secs(300), secs(300)]);
+
+        // behaving for a full max lockout resets the escalation
+        now += secs(300);
+        for _ in 0..3 {
+            throttle.record_failure_at(GUILD, ALICE, now);
+        }
+        assert_eq!(
+            throttle
+                .check_at(GUILD, ALICE, now)
+                .unwrap_err()
+                .retry_after(),
+            secs(100)
+        );
+    }
+
+    #[test]
+    fn pauses_the_guild_after_too_many_failures() {
+        let throttle = throttle();
+        let start = Instant::now();
+
+        for i in 0..5 {
+            let user = UserId::new(100 + i);
+            throttle.record_failure_at(GUILD, user, start + secs(i));
+        }
+
+        let Err(Throttled::Guild { retry_after }) = throttle.check_at(GUILD, BOB, start + secs(10))
+        else {
+            panic!("expected a guild pause");
+        };
+        assert_eq!(retry_after, secs(50));
+        assert!(
+            throttle
+                .check_at(GuildId::new(2), BOB, start + secs(10))
+                .is_ok()
+        );
+        assert!(throttle.check_at(GUILD, BOB, start + secs(61)).is_ok());
+    }
+
+    #[test]
+    fn success_clears_the_user() {
+        let throttle = throttle();
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            throttle.record_failure_at(GUILD, ALICE, now);
+        }
+        assert!(throttle.check_at(GUILD, ALICE, now).is_err());
+
+        throttle.record_success(ALICE);
+        assert!(throttle.check_at(GUILD, ALICE, now).is_ok());
+    }
+
+    #[test]
+    fn forgets_users_once_nothing_is_left_to_remember() {
+        let throttle = throttle();
+        let start = Instant::now();
+
+        throttle.record_failure_at(GUILD, ALICE, start);
+        assert!(throttle.check_at(GUILD, ALICE, start + secs(60)).is_ok());
+        assert!(
+            throttle
+                .users
+                .lock()
+                .expect("Failed to get throttle")
+                .is_empty()
+        );
+
+        for offset in 0..3 {
+            throttle.record_failure_at(GUILD, BOB, start + secs(offset));
+        }
+        // Alice's failure sweeps Bob once his lockout has been forgiven
+        throttle.record_failure_at(GUILD, ALICE, start + secs(2 + 100 + 300));
+        let users = throttle.users.lock().expect("Failed to get throttle");
+        assert!(!users.contains_key(&BOB));
+        assert!(users.contains_key(&ALICE));
+    }
+}
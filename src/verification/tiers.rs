@@ -0,0 +1,74 @@
+use poise::serenity_prelude as serenity;
+use serenity::all::{Member, RoleId};
+
+use crate::{Error, commands::verification::StudentRow, verification::roles::RoleMap};
+
+/// Discord role for each membership type, granted alongside the verified role
+#[derive(Debug, Clone, Default)]
+pub struct TierRoles {
+    roles: RoleMap,
+}
+
+impl TierRoles {
+    pub fn new(roles: RoleMap) -> Self {
+        Self { roles }
+    }
+
+    /// The role for the member's membership type, if it has one
+    pub fn role_for(&self, member: &StudentRow) -> Option<RoleId> {
+        self.roles.get(member.membership_type.as_ref()?)
+    }
+
+    /// Tier roles the Discord member should gain and lose to match their record
+    pub fn changes(
+        &self,
+        member: &Member,
+        record: Option<&StudentRow>,
+    ) -> (Option<RoleId>, Vec<RoleId>) {
+        self.roles
+            .changes(member, record.and_then(|record| self.role_for(record)))
+    }
+
+    /// Gives the Discord member the tier role for their record and removes any other tier roles,
+    /// returning whether anything changed. `None` removes every tier role.
+    pub async fn sync(
+        &self,
+        ctx: &serenity::Context,
+        member: &Member,
+        record: Option<&StudentRow>,
+    ) -> Result<bool, Error> {
+        self.roles
+            .sync(ctx, member, record.and_then(|record| self.role_for(record)))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(membership_type: Option<&str>) -> StudentRow {
+        StudentRow {
+            full_name: "John Doe".to_string(),
+            student_id: "123456789".to_string(),
+            membership_type: membership_type.map(str::to_string),
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn maps_membership_types_to_roles() {
+        let tiers = TierRoles::new(RoleMap::parse("member:1,committee:2").unwrap());
+
+        assert_eq!(
+            tiers.role_for(&record(Some("member"))),
+            Some(RoleId::new(1))
+        );
+        assert_eq!(
+            tiers.role_for(&record(Some("Committee "))),
+            Some(RoleId::new(2))
+        );
+        assert_eq!(tiers.role_for(&record(Some("life"))), None);
+        assert_eq!(tiers.role_for(&record(None)), None);
+    }
+}